@@ -2,6 +2,7 @@ use clap::{Parser, Subcommand};
 use anyhow::{Context, Result};
 use tokio::net::UnixStream;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use core::epilepsy::{Easing, SafetyMode};
 use core::ipc::{IpcCommand, IpcResponse};
 use std::process::exit;
 
@@ -23,10 +24,29 @@ enum Commands {
         #[arg(value_parser = clap::value_parser!(f64))]
         value: f64,
     },
+    /// Set the transition curve (sine, linear, cubic, quintic, exponential, perceptual)
+    Easing {
+        curve: Easing,
+        /// Apply to flashbang dimming instead of regular transitions
+        #[arg(long)]
+        flashbang: bool,
+    },
+    /// Switch safety mode (normal, safe, sleep)
+    Mode {
+        mode: SafetyMode,
+    },
     /// Emergency freeze
-    Freeze,
+    Freeze {
+        /// Freeze duration in seconds (0 = until `resume`)
+        #[arg(short, long, default_value_t = 300)]
+        duration: u64,
+    },
+    /// Leave emergency freeze and ramp back to the previous mode
+    Resume,
     /// Get current status
     Info,
+    /// List recent safety auditor interventions
+    Events,
 }
 
 #[tokio::main]
@@ -35,8 +55,13 @@ async fn main() -> Result<()> {
     
     let command = match cli.command {
         Commands::Set { value } => IpcCommand::SetBrightness(value),
-        Commands::Freeze => IpcCommand::Freeze(300),
+        Commands::Easing { curve, flashbang: false } => IpcCommand::SetEasing(curve),
+        Commands::Easing { curve, flashbang: true } => IpcCommand::SetFlashbangEasing(curve),
+        Commands::Mode { mode } => IpcCommand::SetMode(mode),
+        Commands::Freeze { duration } => IpcCommand::Freeze(duration),
+        Commands::Resume => IpcCommand::Resume,
         Commands::Info => IpcCommand::GetInfo,
+        Commands::Events => IpcCommand::GetSafetyEvents,
         _ => {
             println!("Start/Stop should be managed via systemctl.");
             exit(0);
@@ -54,21 +79,50 @@ async fn send_ipc(cmd: IpcCommand) -> Result<()> {
     let bytes = serde_json::to_vec(&cmd)?;
     stream.write_all(&bytes).await?;
     
-    // Read response
-    let mut buf = [0; 1024];
-    let n = stream.read(&mut buf).await?;
-    if n > 0 {
-        let resp: IpcResponse = serde_json::from_slice(&buf[..n])?;
+    // Read response (the daemon closes the stream after replying)
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await?;
+    if !buf.is_empty() {
+        let resp: IpcResponse = serde_json::from_slice(&buf)?;
         match resp {
             IpcResponse::Ok => println!("OK"),
             IpcResponse::Error(e) => eprintln!("Error: {}", e),
-            IpcResponse::Status { brightness, location, wake_time, transition_duration_ms, flashbang_protection } => {
+            IpcResponse::Status { brightness, location, wake_time, transition_duration_ms, flashbang_protection, safety_events, easing, flashbang_easing, frozen, freeze_remaining_secs, mode, backends, ambient_lux } => {
                 println!("--- AutoBrightness Status ---");
                 println!("Brightness:       {:.1}%", brightness);
+                println!("Mode:             {:?}", mode);
                 println!("Location:         {}", location);
                 println!("Wake Time:        {}", wake_time);
                 println!("Transition Time:  {}ms", transition_duration_ms);
                 println!("Flashbang Prot.:  {}", if flashbang_protection { "ON" } else { "OFF" });
+                println!("Easing:           {:?} (flashbang: {:?})", easing, flashbang_easing);
+                println!("Safety Events:    {}", safety_events);
+                if frozen {
+                    match freeze_remaining_secs {
+                        Some(secs) => println!("Emergency Stop:   {}m {:02}s remaining", secs / 60, secs % 60),
+                        None => println!("Emergency Stop:   until resumed"),
+                    }
+                }
+                if let Some(lux) = ambient_lux {
+                    println!("Ambient Light:    {:.0} lux", lux);
+                }
+                for b in backends {
+                    println!("Backend:          {} via {} ({}{})", b.display, b.backend, b.reason,
+                             if b.failures > 0 { format!(", {} failed writes", b.failures) } else { String::new() });
+                    if !b.standby.is_empty() {
+                        println!("  Standby:        {}", b.standby.join(", "));
+                    }
+                }
+            }
+            IpcResponse::SafetyEvents(events) => {
+                if events.is_empty() {
+                    println!("No safety interventions recorded");
+                }
+                for e in events {
+                    let applied = e.applied.map(|v| format!("{:.1}%", v)).unwrap_or_else(|| "-".to_string());
+                    println!("{}  {:<9?}  requested {:.1}%  applied {}  ({} reversals)",
+                             e.timestamp.format("%Y-%m-%d %H:%M:%S"), e.action, e.requested, applied, e.reversals);
+                }
             }
         }
     } else {
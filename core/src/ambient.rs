@@ -0,0 +1,311 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use crate::clock::SharedClock;
+use crate::hardware::HardwareError;
+use crate::sysfs::SysfsRoot;
+
+// Readings kept for the median; spikes shorter than half of it never reach the output
+const MEDIAN_WINDOW: usize = 5;
+
+pub trait LightSensor {
+    fn read_lux(&mut self) -> Result<f64, HardwareError>;
+    fn name(&self) -> &str;
+}
+
+pub type BoxedLightSensor = Box<dyn LightSensor + Send>;
+
+// Light has to change by this much from the last accepted reading before the
+// curve is evaluated again, so flicker and passing shadows cause no adjustments
+pub const DEFAULT_LUX_HYSTERESIS_PERCENT: f64 = 25.0;
+
+// Share of the automatic target that comes from the sensor, the rest is circadian
+pub const DEFAULT_AMBIENT_WEIGHT: f64 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LuxKeyframe {
+    pub lux: f64,
+    pub brightness: f64,
+}
+
+// Dark room 10%, office (~300 lux) about 66%, daylight at a window 100%
+pub fn default_lux_curve() -> Vec<LuxKeyframe> {
+    [(0.0, 10.0), (10.0, 33.0), (100.0, 55.0), (1000.0, 78.0), (10000.0, 100.0)].iter()
+        .map(|&(lux, brightness)| LuxKeyframe { lux, brightness })
+        .collect()
+}
+
+// Piecewise linear interpolation over keyframes given as (x, y) by `point`,
+// ascending in x and measured on `axis`; flat beyond the first and last one
+pub fn interpolate_keyframes<K>(curve: &[K], x: f64, point: impl Fn(&K) -> (f64, f64), axis: impl Fn(f64) -> f64) -> Option<f64> {
+    let x = axis(x);
+    let (first_x, first_y) = point(curve.first()?);
+    if x <= axis(first_x) {
+        return Some(first_y);
+    }
+    for pair in curve.windows(2) {
+        let ((x0, y0), (x1, y1)) = (point(&pair[0]), point(&pair[1]));
+        let (a, b) = (axis(x0), axis(x1));
+        if x <= b {
+            let t = if b > a { (x - a) / (b - a) } else { 1.0 };
+            return Some(y0 + (y1 - y0) * t);
+        }
+    }
+    curve.last().map(|k| point(k).1)
+}
+
+// Keyframes (ascending lux) interpolated on a log10(lux + 1) axis, the eye's response
+pub fn curve_brightness(curve: &[LuxKeyframe], lux: f64) -> Option<f64> {
+    interpolate_keyframes(curve, lux, |k| (k.lux, k.brightness), |lux| (lux.max(0.0) + 1.0).log10())
+}
+
+// The configured curve bounded by min/max brightness, behind a hysteresis band
+pub struct LuxResponse {
+    curve: Vec<LuxKeyframe>,
+    min_brightness: f64,
+    max_brightness: f64,
+    hysteresis_percent: f64,
+    accepted_lux: Option<f64>,
+}
+
+impl LuxResponse {
+    pub fn new(curve: Vec<LuxKeyframe>, min_brightness: f64, max_brightness: f64, hysteresis_percent: f64) -> Self {
+        Self { curve, min_brightness, max_brightness, hysteresis_percent, accepted_lux: None }
+    }
+
+    pub fn brightness(&self, lux: f64) -> f64 {
+        curve_brightness(&self.curve, lux).unwrap_or(self.max_brightness).clamp(self.min_brightness, self.max_brightness)
+    }
+
+    // Compared as lux + 1 so the band does not collapse in a dark room
+    pub fn target(&mut self, lux: f64) -> f64 {
+        let ratio = 1.0 + self.hysteresis_percent / 100.0;
+        let outside_band = self.accepted_lux.is_none_or(|accepted| {
+            let (accepted, lux) = (accepted + 1.0, lux + 1.0);
+            lux > accepted * ratio || lux < accepted / ratio
+        });
+        if outside_band {
+            debug!("Ambient light: {:.0} lux accepted", lux);
+            self.accepted_lux = Some(lux);
+        }
+        self.brightness(self.accepted_lux.unwrap_or(lux))
+    }
+}
+
+impl Default for LuxResponse {
+    fn default() -> Self {
+        Self::new(default_lux_curve(), 0.0, 100.0, DEFAULT_LUX_HYSTERESIS_PERCENT)
+    }
+}
+
+// Median over the last few readings drops outliers (a hand over the sensor,
+// a camera flash), then an exponential average on the log axis smooths the rest
+pub struct LuxFilter {
+    window: VecDeque<f64>,
+    smoothing: Duration,
+    level: Option<f64>, // log10(lux + 1)
+    last_update: Option<Instant>,
+}
+
+impl LuxFilter {
+    pub fn new(smoothing: Duration) -> Self {
+        Self { window: VecDeque::with_capacity(MEDIAN_WINDOW), smoothing, level: None, last_update: None }
+    }
+
+    pub fn push(&mut self, lux: f64, now: Instant) -> Option<f64> {
+        if !lux.is_finite() || lux < 0.0 {
+            return self.lux();
+        }
+        if self.window.len() == MEDIAN_WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(lux);
+        let mut sorted: Vec<f64> = self.window.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let median = (sorted[(sorted.len() - 1) / 2] + 1.0).log10();
+
+        self.level = Some(match (self.level, self.last_update) {
+            (Some(level), Some(last)) if !self.smoothing.is_zero() => {
+                let dt = now.saturating_duration_since(last).as_secs_f64();
+                let alpha = 1.0 - (-dt / self.smoothing.as_secs_f64()).exp();
+                level + (median - level) * alpha
+            }
+            _ => median,
+        });
+        self.last_update = Some(now);
+        self.lux()
+    }
+
+    pub fn lux(&self) -> Option<f64> {
+        self.level.map(|l| 10f64.powf(l) - 1.0)
+    }
+}
+
+// An IIO light sensor read through sysfs: in_illuminance*_input is already in
+// lux, otherwise in_illuminance*_raw is corrected with its _offset and _scale
+pub struct IioLightSensor {
+    name: String,
+    path: PathBuf,
+    offset: f64,
+    scale: f64,
+}
+
+impl IioLightSensor {
+    pub fn discover() -> Result<Self, HardwareError> {
+        Self::discover_in(&SysfsRoot::default())
+    }
+
+    pub fn discover_in(sysfs: &SysfsRoot) -> Result<Self, HardwareError> {
+        let mut devices: Vec<PathBuf> = fs::read_dir(sysfs.iio_devices())?.flatten().map(|e| e.path()).collect();
+        devices.sort();
+        devices.iter().find_map(|d| Self::open(d)).ok_or(HardwareError::NotSupported)
+    }
+
+    fn open(device: &Path) -> Option<Self> {
+        let mut files: Vec<String> = fs::read_dir(device).ok()?
+            .flatten()
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|f| f.starts_with("in_illuminance"))
+            .collect();
+        files.sort();
+        let label = fs::read_to_string(device.join("name")).map(|n| n.trim().to_string())
+            .unwrap_or_else(|_| device.file_name().unwrap_or_default().to_string_lossy().into_owned());
+        let name = format!("IIO ({})", label);
+
+        if let Some(input) = files.iter().find(|f| f.ends_with("_input")) {
+            return Some(Self { name, path: device.join(input), offset: 0.0, scale: 1.0 });
+        }
+        let raw = files.iter().find(|f| f.ends_with("_raw"))?;
+        // Channel specific attribute first, then the one shared by every illuminance channel
+        let attribute = |suffix: &str, default: f64| {
+            [raw.replace("_raw", suffix), format!("in_illuminance{}", suffix)].iter()
+                .find_map(|f| fs::read_to_string(device.join(f)).ok()?.trim().parse::<f64>().ok())
+                .unwrap_or(default)
+        };
+        let (offset, scale) = (attribute("_offset", 0.0), attribute("_scale", 1.0));
+        Some(Self { name, path: device.join(raw), offset, scale })
+    }
+}
+
+impl LightSensor for IioLightSensor {
+    fn read_lux(&mut self) -> Result<f64, HardwareError> {
+        let value = fs::read_to_string(&self.path)?.trim().parse::<f64>()
+            .map_err(|_| HardwareError::CommandFailed(format!("Unreadable value in {}", self.path.display())))?;
+        Ok((value + self.offset) * self.scale)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+// iio-sensor-proxy: shares the sensor with the desktop and knows the
+// per-device quirks. The claim is released on drop (or when the bus connection closes).
+pub struct SensorProxyLight {
+    connection: zbus::blocking::Connection,
+}
+
+impl SensorProxyLight {
+    const DEST: &'static str = "net.hadess.SensorProxy";
+    const PATH: &'static str = "/net/hadess/SensorProxy";
+
+    pub fn new() -> Result<Self, HardwareError> {
+        let connection = zbus::blocking::Connection::system()
+            .map_err(|e| HardwareError::Io(std::io::Error::other(e.to_string())))?;
+        let sensor = Self { connection };
+
+        if !bool::try_from(sensor.property("HasAmbientLight")?).unwrap_or(false) {
+            return Err(HardwareError::NotSupported);
+        }
+        // "vendor" units are only comparable on the same machine
+        let unit = String::try_from(sensor.property("LightLevelUnit")?).unwrap_or_default();
+        if unit != "lux" {
+            warn!("iio-sensor-proxy reports light in '{}' units, not lux; ignoring it", unit);
+            return Err(HardwareError::NotSupported);
+        }
+        sensor.call("ClaimLight")?;
+        Ok(sensor)
+    }
+
+    fn call(&self, method: &str) -> Result<(), HardwareError> {
+        self.connection.call_method(Some(Self::DEST), Self::PATH, Some(Self::DEST), method, &())
+            .map_err(|e| HardwareError::CommandFailed(format!("SensorProxy.{} Error: {}", method, e)))?;
+        Ok(())
+    }
+
+    fn property(&self, name: &str) -> Result<zbus::zvariant::OwnedValue, HardwareError> {
+        let reply = self.connection.call_method(
+            Some(Self::DEST),
+            Self::PATH,
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &(Self::DEST, name),
+        )
+        .map_err(|e| HardwareError::CommandFailed(format!("DBus Properties.Get Error: {}", e)))?;
+        reply.body().deserialize()
+            .map_err(|e| HardwareError::CommandFailed(format!("Deserialize Error: {}", e)))
+    }
+}
+
+impl LightSensor for SensorProxyLight {
+    fn read_lux(&mut self) -> Result<f64, HardwareError> {
+        f64::try_from(self.property("LightLevel")?)
+            .map_err(|_| HardwareError::CommandFailed("Unexpected property type".into()))
+    }
+
+    fn name(&self) -> &str {
+        "iio-sensor-proxy"
+    }
+}
+
+impl Drop for SensorProxyLight {
+    fn drop(&mut self) {
+        self.call("ReleaseLight").ok();
+    }
+}
+
+// A light sensor and the filter its readings go through
+pub struct AmbientLightSource {
+    sensor: BoxedLightSensor,
+    filter: LuxFilter,
+    clock: SharedClock,
+}
+
+impl AmbientLightSource {
+    pub fn new(sensor: BoxedLightSensor, smoothing: Duration, clock: SharedClock) -> Self {
+        Self { sensor, filter: LuxFilter::new(smoothing), clock }
+    }
+
+    // "auto" prefers iio-sensor-proxy, which may already hold the sensor, over raw sysfs
+    pub fn discover(method: &str, sysfs: &SysfsRoot) -> Option<BoxedLightSensor> {
+        let proxy = || SensorProxyLight::new().ok().map(|s| Box::new(s) as BoxedLightSensor);
+        let iio = || IioLightSensor::discover_in(sysfs).ok().map(|s| Box::new(s) as BoxedLightSensor);
+        let sensor = match method {
+            "sensorproxy" => proxy(),
+            "iio" => iio(),
+            "auto" => proxy().or_else(iio),
+            _ => None,
+        };
+        if sensor.is_none() {
+            info!("No ambient light sensor found (method '{}')", method);
+        }
+        sensor
+    }
+
+    pub fn name(&self) -> &str {
+        self.sensor.name()
+    }
+
+    // Smoothed lux after this reading
+    pub fn poll(&mut self) -> Result<Option<f64>, HardwareError> {
+        let lux = self.sensor.read_lux()?;
+        Ok(self.filter.push(lux, self.clock.now()))
+    }
+
+    pub fn lux(&self) -> Option<f64> {
+        self.filter.lux()
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use crate::ambient::{curve_brightness, IioLightSensor, LightSensor, LuxFilter, LuxKeyframe, LuxResponse};
+    use crate::clock::{Clock, ManualClock};
+    use crate::config::Config;
+    use crate::context::ContextManager;
+    use crate::sysfs::SysfsRoot;
+    use chrono::Utc;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    fn fake_iio(name: &str, files: &[(&str, &str)]) -> SysfsRoot {
+        let root = SysfsRoot::new(std::env::temp_dir().join(format!("epilyzer-iio-{}-{}", name, std::process::id())));
+        let device = root.iio_devices().join("iio:device0");
+        fs::create_dir_all(&device).unwrap();
+        for (file, content) in files {
+            fs::write(device.join(file), content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_lux_filter_rejects_spikes() {
+        let start = Instant::now();
+        let mut filter = LuxFilter::new(Duration::from_secs(5));
+        assert!((filter.push(100.0, start).unwrap() - 100.0).abs() < 1e-9);
+
+        // A two-sample flash into the sensor never shows up
+        let mut t = start;
+        for lux in [100.0, 50000.0, 40000.0, 100.0, 100.0] {
+            t += Duration::from_secs(1);
+            let smoothed = filter.push(lux, t).unwrap();
+            assert!((smoothed - 100.0).abs() < 1.0, "{}", smoothed);
+        }
+        // Garbage readings are ignored
+        assert!((filter.push(f64::NAN, t).unwrap() - 100.0).abs() < 1.0);
+
+        // A real change (lights off) passes the median and settles over a few time constants
+        let mut previous = filter.lux().unwrap();
+        for _ in 0..30 {
+            t += Duration::from_secs(1);
+            let smoothed = filter.push(5.0, t).unwrap();
+            assert!(smoothed <= previous);
+            previous = smoothed;
+        }
+        assert!((previous - 5.0).abs() < 0.5, "{}", previous);
+    }
+
+    #[test]
+    fn test_iio_sensor_discovery() {
+        let root = fake_iio("input", &[("name", "acpi-als\n"), ("in_illuminance_input", "321\n")]);
+        let mut sensor = IioLightSensor::discover_in(&root).unwrap();
+        assert_eq!(sensor.name(), "IIO (acpi-als)");
+        assert_eq!(sensor.read_lux().unwrap(), 321.0);
+        fs::remove_dir_all(root.path()).ok();
+
+        let root = fake_iio("raw", &[("in_illuminance0_raw", "200\n"), ("in_illuminance_scale", "0.5\n"), ("in_illuminance0_offset", "10\n")]);
+        let mut sensor = IioLightSensor::discover_in(&root).unwrap();
+        assert_eq!(sensor.read_lux().unwrap(), 105.0);
+        fs::remove_dir_all(root.path()).ok();
+
+        let root = fake_iio("none", &[("in_accel_x_raw", "1\n")]);
+        assert!(IioLightSensor::discover_in(&root).is_err());
+        fs::remove_dir_all(root.path()).ok();
+    }
+
+    #[test]
+    fn test_lux_curve_and_hysteresis() {
+        let curve = vec![LuxKeyframe { lux: 0.0, brightness: 5.0 }, LuxKeyframe { lux: 99.0, brightness: 60.0 }, LuxKeyframe { lux: 9999.0, brightness: 100.0 }];
+        // Halfway between 0 and 99 lux on the log axis is 9 lux
+        assert!((curve_brightness(&curve, 9.0).unwrap() - 32.5).abs() < 1e-9);
+        assert_eq!(curve_brightness(&curve, 1e6), Some(100.0));
+        assert_eq!(curve_brightness(&[], 10.0), None);
+
+        // Bounded by min/max brightness
+        let mut response = LuxResponse::new(curve, 15.0, 95.0, 25.0);
+        assert_eq!(response.brightness(0.0), 15.0);
+        assert_eq!(response.brightness(1e6), 95.0);
+
+        // A flickering lamp around 99 lux stays inside the band
+        let settled = response.target(99.0);
+        for lux in [115.0, 85.0, 120.0, 82.0] {
+            assert_eq!(response.target(lux), settled);
+        }
+        // Lights dimmed for real
+        assert!(response.target(40.0) < settled);
+    }
+
+    #[test]
+    fn test_ambient_blends_with_circadian() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut ctx = ContextManager::with_clock(&Config::default().location, "07:00", clock.clone());
+        let now = clock.utc_now();
+        let circadian = ctx.get_circadian_target(now);
+        let ambient = LuxResponse::default().brightness(300.0);
+
+        ctx.set_ambient_response(LuxResponse::default(), 0.7);
+        let blended = ctx.get_automatic_target(now, 1.0, Some(300.0));
+        assert!((blended - (ambient * 0.7 + circadian * 0.3)).abs() < 1e-9);
+
+        // Without a sensor the weather still scales the circadian curve
+        assert!((ctx.get_automatic_target(now, 0.8, None) - circadian * 0.8).abs() < 1e-9);
+        ctx.set_ambient_response(LuxResponse::default(), 0.0);
+        assert_eq!(ctx.get_automatic_target(now, 1.0, Some(300.0)), circadian);
+    }
+}
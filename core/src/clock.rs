@@ -0,0 +1,69 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+
+// Source of time for everything that schedules brightness changes. The daemon uses
+// SystemClock; tests and simulations drive a ManualClock so hours of safety
+// behaviour run in milliseconds without sleeping.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn utc_now(&self) -> DateTime<Utc>;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+// Virtual time that only moves when advanced. Both the monotonic and the wall
+// clock advance together.
+pub struct ManualClock {
+    base_instant: Instant,
+    base_utc: DateTime<Utc>,
+    offset: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            base_instant: Instant::now(),
+            base_utc: start,
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn shared(start: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(Self::new(start))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.offset.lock().unwrap() += by;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        *self.offset.lock().unwrap()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.base_instant + self.elapsed()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        self.base_utc + chrono::Duration::from_std(self.elapsed()).unwrap_or_else(|_| chrono::Duration::zero())
+    }
+}
@@ -1,8 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
-use crate::epilepsy::{MIN_TRANSITION_TIME_SEC, MAX_CHANGE_FREQUENCY_HZ};
+use crate::hotkey::Hotkey;
+use crate::ambient::{default_lux_curve, LuxKeyframe, DEFAULT_AMBIENT_WEIGHT, DEFAULT_LUX_HYSTERESIS_PERCENT};
+use crate::context::DEFAULT_NIGHT_KELVIN;
+use crate::gamma::GammaMode;
+use crate::hardware::{MIN_KELVIN, NEUTRAL_KELVIN};
+use crate::keyboard::{default_keyboard_curve, KeyboardKeyframe};
+use crate::sysfs::SysfsRoot;
+use crate::epilepsy::{BrightnessSpace, Easing, SafetyMode, DEFAULT_SLEEP_MODE_BRIGHTNESS, MIN_TRANSITION_TIME_SEC, MAX_CHANGE_FREQUENCY_HZ};
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -20,6 +27,14 @@ pub struct Config {
     pub location: LocationConfig,
     pub epilepsy_protection: EpilepsyConfig,
     pub brightness: BrightnessConfig,
+    #[serde(default)]
+    pub displays: Vec<DisplayConfig>,
+    #[serde(default)]
+    pub color: ColorConfig,
+    #[serde(default)]
+    pub ambient: AmbientConfig,
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,6 +44,8 @@ pub struct GeneralConfig {
     pub log_level: String,
     #[serde(default = "default_wake_time")]
     pub wake_time: String, // "HH:MM"
+    #[serde(default)]
+    pub sysfs_root: Option<PathBuf>, // Fake device tree for tests and demos, else $EPILYZER_SYSFS_ROOT or /sys
 }
 
 fn default_wake_time() -> String {
@@ -51,20 +68,145 @@ pub struct EpilepsyConfig {
     pub smooth_steps: u32,
     pub emergency_hotkey: String,
     pub safe_mode_brightness: f64,
+    #[serde(default = "default_sleep_mode_brightness")]
+    pub sleep_mode_brightness: f64,
     #[serde(default = "default_transition_duration_ms")]
     pub transition_duration_ms: u64,
+    #[serde(default = "default_viewing_distance_cm")]
+    pub viewing_distance_cm: f64, // Used to size the WCAG 10° visual field
+    #[serde(default = "default_screen_dpi")]
+    pub screen_dpi: f64,
+    #[serde(default = "default_auditor_min_delta")]
+    pub auditor_min_delta: f64, // Smallest swing (%) the SafetyAuditor treats as a reversal
+    #[serde(default)]
+    pub easing: Easing, // Circadian drift and manual changes
+    #[serde(default)]
+    pub flashbang_easing: Easing, // Fast flashbang dimming
 }
 
 fn default_transition_duration_ms() -> u64 {
     750
 }
 
+fn default_sleep_mode_brightness() -> f64 {
+    DEFAULT_SLEEP_MODE_BRIGHTNESS
+}
+
+fn default_viewing_distance_cm() -> f64 {
+    60.0
+}
+
+fn default_screen_dpi() -> f64 {
+    96.0
+}
+
+fn default_auditor_min_delta() -> f64 {
+    5.0
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BrightnessConfig {
-    pub method: String, // "ddcutil", "backlight"
+    pub method: String, // "auto" (every display), "ddcutil", "backlight"
     pub min_brightness: f64,
     pub max_brightness: f64,
     pub default_brightness: f64,
+    #[serde(default)]
+    pub space: BrightnessSpace, // Where transitions interpolate: "perceptual" (CIE L*) or "linear"; values stay backlight percent
+    #[serde(default)]
+    pub gamma: GammaMode, // "off", "fallback" (outputs without other control), "extend" (also below hardware_floor)
+    #[serde(default = "default_hardware_floor")]
+    pub hardware_floor: f64, // Lowest hardware percent before gamma takes over in "extend"
+    #[serde(default = "default_lux_curve")]
+    pub lux_curve: Vec<LuxKeyframe>, // Ascending lux, interpolated on a log axis
+    #[serde(default = "default_lux_hysteresis")]
+    pub lux_hysteresis: f64, // Percent the light must change by before the target follows
+    #[serde(default = "default_ambient_weight")]
+    pub ambient_weight: f64, // 0 = circadian only, 1 = light sensor only
+}
+
+fn default_hardware_floor() -> f64 {
+    10.0
+}
+
+fn default_lux_hysteresis() -> f64 {
+    DEFAULT_LUX_HYSTERESIS_PERCENT
+}
+
+fn default_ambient_weight() -> f64 {
+    DEFAULT_AMBIENT_WEIGHT
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColorConfig {
+    pub method: String, // "auto", "kde", "gnome", "gammastep" (also finds redshift), "off"
+    pub day_kelvin: u32,
+    pub night_kelvin: u32,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            method: "auto".to_string(),
+            day_kelvin: NEUTRAL_KELVIN,
+            night_kelvin: DEFAULT_NIGHT_KELVIN,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AmbientConfig {
+    pub method: String, // "auto", "sensorproxy", "iio", "off"
+    #[serde(default = "default_lux_smoothing_secs")]
+    pub smoothing_secs: f64, // Time constant of the lux average
+}
+
+fn default_lux_smoothing_secs() -> f64 {
+    5.0
+}
+
+impl Default for AmbientConfig {
+    fn default() -> Self {
+        Self {
+            method: "auto".to_string(),
+            smoothing_secs: default_lux_smoothing_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyboardConfig {
+    pub enabled: bool,
+    #[serde(default = "default_keyboard_curve")]
+    pub curve: Vec<KeyboardKeyframe>, // Automatic screen target -> keyboard percent
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            curve: default_keyboard_curve(),
+        }
+    }
+}
+
+// Per-display adjustment of the shared target: value * scale + offset
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DisplayConfig {
+    pub id: String, // As logged at startup, e.g. "backlight:intel_backlight", "ddc:i2c-4"
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default = "default_display_scale")]
+    pub scale: f64,
+    #[serde(default = "default_display_enabled")]
+    pub enabled: bool,
+}
+
+fn default_display_scale() -> f64 {
+    1.0
+}
+
+fn default_display_enabled() -> bool {
+    true
 }
 
 impl Default for Config {
@@ -75,6 +217,7 @@ impl Default for Config {
                 mode: "normal".to_string(),
                 log_level: "info".to_string(),
                 wake_time: "07:00".to_string(),
+                sysfs_root: None,
             },
             location: LocationConfig {
                 method: "auto".to_string(),
@@ -89,14 +232,30 @@ impl Default for Config {
                 smooth_steps: 50,
                 emergency_hotkey: "Ctrl+Alt+B".to_string(),
                 safe_mode_brightness: 40.0,
+                sleep_mode_brightness: DEFAULT_SLEEP_MODE_BRIGHTNESS,
                 transition_duration_ms: 750,
+                viewing_distance_cm: 60.0,
+                screen_dpi: 96.0,
+                auditor_min_delta: 5.0,
+                easing: Easing::Sine,
+                flashbang_easing: Easing::Sine,
             },
             brightness: BrightnessConfig {
-                method: "ddcutil".to_string(),
+                method: "auto".to_string(),
                 min_brightness: 15.0,
                 max_brightness: 95.0,
                 default_brightness: 50.0,
+                space: BrightnessSpace::Perceptual,
+                gamma: GammaMode::Fallback,
+                hardware_floor: default_hardware_floor(),
+                lux_curve: default_lux_curve(),
+                lux_hysteresis: DEFAULT_LUX_HYSTERESIS_PERCENT,
+                ambient_weight: DEFAULT_AMBIENT_WEIGHT,
             },
+            displays: Vec::new(),
+            color: ColorConfig::default(),
+            ambient: AmbientConfig::default(),
+            keyboard: KeyboardConfig::default(),
         }
     }
 }
@@ -110,7 +269,47 @@ impl Config {
         if config.epilepsy_protection.min_transition_time < 0.5 {
              return Err(ConfigError::Validation("Transition time too short for safety".to_string()));
         }
+        if config.displays.iter().any(|d| d.scale <= 0.0) {
+             return Err(ConfigError::Validation("Display scale must be positive".to_string()));
+        }
+        let kelvin_range = MIN_KELVIN..=NEUTRAL_KELVIN;
+        if !kelvin_range.contains(&config.color.day_kelvin) || !kelvin_range.contains(&config.color.night_kelvin)
+            || config.color.night_kelvin > config.color.day_kelvin {
+             return Err(ConfigError::Validation(format!("Colour temperatures must satisfy {} <= night_kelvin <= day_kelvin <= {}", MIN_KELVIN, NEUTRAL_KELVIN)));
+        }
+        if !(1.0..=100.0).contains(&config.brightness.hardware_floor) {
+             return Err(ConfigError::Validation("Hardware floor must be between 1 and 100".to_string()));
+        }
+        let curve = &config.brightness.lux_curve;
+        if curve.is_empty() || curve.windows(2).any(|k| k[1].lux <= k[0].lux)
+            || curve.iter().any(|k| k.lux < 0.0 || !(0.0..=100.0).contains(&k.brightness)) {
+             return Err(ConfigError::Validation("lux_curve needs keyframes with ascending lux >= 0 and brightness between 0 and 100".to_string()));
+        }
+        if !(0.0..=1.0).contains(&config.brightness.ambient_weight) || !(0.0..=1000.0).contains(&config.brightness.lux_hysteresis) {
+             return Err(ConfigError::Validation("ambient_weight must be between 0 and 1, lux_hysteresis between 0 and 1000%".to_string()));
+        }
+        let curve = &config.keyboard.curve;
+        if curve.is_empty() || curve.windows(2).any(|k| k[1].screen <= k[0].screen)
+            || curve.iter().any(|k| !(0.0..=100.0).contains(&k.screen) || !(0.0..=100.0).contains(&k.keyboard)) {
+             return Err(ConfigError::Validation("keyboard curve needs keyframes with ascending screen values, all between 0 and 100".to_string()));
+        }
+        if !(0.0..=600.0).contains(&config.ambient.smoothing_secs) {
+             return Err(ConfigError::Validation("Ambient light smoothing must be between 0 and 600 seconds".to_string()));
+        }
+        if config.epilepsy_protection.viewing_distance_cm <= 0.0 || config.epilepsy_protection.screen_dpi <= 0.0 {
+             return Err(ConfigError::Validation("Viewing distance and screen DPI must be positive".to_string()));
+        }
+        config.general.mode.parse::<SafetyMode>().map_err(ConfigError::Validation)?;
+        config.epilepsy_protection.emergency_hotkey.parse::<Hotkey>().map_err(ConfigError::Validation)?;
         
         Ok(config)
     }
+
+    pub fn sysfs_root(&self) -> SysfsRoot {
+        SysfsRoot::resolve(self.general.sysfs_root.as_deref())
+    }
+
+    pub fn display(&self, id: &str) -> Option<&DisplayConfig> {
+        self.displays.iter().find(|d| d.id == id)
+    }
 }
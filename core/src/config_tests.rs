@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::ManualClock;
+    use crate::config::Config;
+    use crate::epilepsy::{BrightnessSpace, EpilepsyGuard};
+    use chrono::Utc;
+    use std::fs;
+    use std::time::Duration;
+
+    // Writes `config` without its `space` key, the way files from before the setting look
+    fn load_without_space(name: &str, config: &Config) -> Config {
+        let legacy: String = toml::to_string(config).unwrap()
+            .lines()
+            .filter(|line| !line.starts_with("space"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert!(!legacy.contains("space ="));
+        let path = std::env::temp_dir().join(format!("epilyzer-config-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, legacy).unwrap();
+        let loaded = Config::load_from_file(&path).unwrap();
+        fs::remove_file(&path).ok();
+        loaded
+    }
+
+    // Samples a 20% -> `target` transition in `space`, the last sample is where it lands
+    fn ramp(space: BrightnessSpace, target: f64) -> Vec<f64> {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(20.0, clock.clone());
+        guard.set_space(space);
+        guard.request_transition(target);
+        let mut samples = Vec::new();
+        while guard.transition.is_some() {
+            clock.advance(Duration::from_millis(8));
+            samples.extend(guard.tick_transition());
+        }
+        samples
+    }
+
+    #[test]
+    fn test_perceptual_space_by_default() {
+        let mut config = Config::default();
+        config.brightness.default_brightness = 50.0;
+        let loaded = load_without_space("legacy", &config);
+        assert_eq!(loaded.brightness.space, BrightnessSpace::Perceptual);
+        assert_eq!(Config::default().brightness.space, BrightnessSpace::Perceptual);
+
+        // A saved or configured 50% still drives the backlight to 50%
+        let perceptual = ramp(loaded.brightness.space, loaded.brightness.default_brightness);
+        assert!((perceptual.last().unwrap() - 50.0).abs() < 1e-9);
+
+        // On the way there the backlight moves in even steps of lightness,
+        // slower at the bright end than a linear ramp
+        let linear = ramp(BrightnessSpace::Linear, 50.0);
+        let half = perceptual.len() / 2;
+        assert!(perceptual[half] < linear[half] - 1.0, "{} vs {}", perceptual[half], linear[half]);
+    }
+
+    #[test]
+    fn test_linear_space_is_opt_in() {
+        let mut config = Config::default();
+        config.brightness.space = BrightnessSpace::Linear;
+        let path = std::env::temp_dir().join(format!("epilyzer-config-linear-{}.toml", std::process::id()));
+        fs::write(&path, toml::to_string(&config).unwrap()).unwrap();
+        let loaded = Config::load_from_file(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(loaded.brightness.space, BrightnessSpace::Linear);
+    }
+}
@@ -1,15 +1,29 @@
 use chrono::{DateTime, Utc, Timelike, NaiveTime, Datelike};
 use tracing::info;
 use crate::config::LocationConfig;
+use crate::clock::{system_clock, SharedClock};
+use crate::ambient::{LuxResponse, DEFAULT_AMBIENT_WEIGHT};
+use crate::hardware::NEUTRAL_KELVIN;
+
+pub const DEFAULT_NIGHT_KELVIN: u32 = 3400;
 
 pub struct ContextManager {
     _lat: f64,
     lon: f64,
     wake_time: chrono::NaiveTime,
+    clock: SharedClock,
+    day_kelvin: u32,
+    night_kelvin: u32,
+    lux_response: LuxResponse,
+    ambient_weight: f64,
 }
 
 impl ContextManager {
     pub fn new(config: &LocationConfig, wake_time_str: &str) -> Self {
+        Self::with_clock(config, wake_time_str, system_clock())
+    }
+
+    pub fn with_clock(config: &LocationConfig, wake_time_str: &str, clock: SharedClock) -> Self {
         let lat = config.latitude.unwrap_or(41.0082);
         let lon = config.longitude.unwrap_or(28.9784);
         
@@ -18,7 +32,34 @@ impl ContextManager {
 
         info!("Context initialized at Lat: {}, Lon: {}, Wake: {}", lat, lon, wake_time);
         
-        Self { _lat: lat, lon, wake_time }
+        Self {
+            _lat: lat,
+            lon,
+            wake_time,
+            clock,
+            day_kelvin: NEUTRAL_KELVIN,
+            night_kelvin: DEFAULT_NIGHT_KELVIN,
+            lux_response: LuxResponse::default(),
+            ambient_weight: DEFAULT_AMBIENT_WEIGHT,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.utc_now()
+    }
+
+    pub fn get_circadian_target_now(&self) -> f64 {
+        self.get_circadian_target(self.now())
+    }
+
+    pub fn set_kelvin_range(&mut self, day: u32, night: u32) {
+        self.day_kelvin = day;
+        self.night_kelvin = night.min(day);
+    }
+
+    pub fn set_ambient_response(&mut self, response: LuxResponse, weight: f64) {
+        self.lux_response = response;
+        self.ambient_weight = weight.clamp(0.0, 1.0);
     }
 
     pub fn get_wake_time(&self) -> (u8, u8) {
@@ -76,8 +117,7 @@ impl ContextManager {
         let elevation = self.calculate_solar_elevation(now);
         
         // Check wake time override (simple check)
-        let now_local = now.hour() + 3; // Approx
-        if now_local < self.wake_time.hour() {
+        if self.is_before_wake(now) {
              return 10.0; // Sleep brightness
         }
 
@@ -107,4 +147,42 @@ impl ContextManager {
         
         target_b
     }
+
+    // The circadian curve scaled by the weather. With a light sensor, blended
+    // with the lux curve (which already sees the weather) by ambient_weight.
+    pub fn get_automatic_target(&mut self, now: DateTime<Utc>, weather: f64, ambient_lux: Option<f64>) -> f64 {
+        let circadian = self.get_circadian_target(now);
+        match ambient_lux {
+            Some(lux) => {
+                let ambient = self.lux_response.target(lux);
+                ambient * self.ambient_weight + circadian * (1.0 - self.ambient_weight)
+            }
+            None if weather < 0.99 => circadian * weather,
+            None => circadian,
+        }
+    }
+
+    // Same elevation bands as the brightness curve: neutral by day, warming
+    // through civil twilight, night_kelvin from -6° on and before wake time
+    pub fn get_kelvin_target(&self, now: DateTime<Utc>) -> u32 {
+        if self.is_before_wake(now) {
+            return self.night_kelvin;
+        }
+        let elevation = self.calculate_solar_elevation(now);
+        let day = self.day_kelvin as f64;
+        let night = self.night_kelvin as f64;
+        let kelvin = if elevation > 6.0 {
+            day
+        } else if elevation > -6.0 {
+            night + (day - night) * (elevation + 6.0) / 12.0
+        } else {
+            night
+        };
+        kelvin.round() as u32
+    }
+
+    fn is_before_wake(&self, now: DateTime<Utc>) -> bool {
+        let now_local = now.hour() + 3; // Approx
+        now_local < self.wake_time.hour()
+    }
 }
@@ -0,0 +1,392 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use crate::failover::BackendStatus;
+use crate::hardware::{BackendCapabilities, BrightnessController, HardwareError, Privilege};
+use crate::sysfs::SysfsRoot;
+
+// i2c-dev ioctl (linux/i2c-dev.h)
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+
+pub const DDC_CI_ADDR: u16 = 0x37;
+pub const EDID_ADDR: u16 = 0x50;
+const HOST_ADDR: u8 = 0x51; // Source address byte in host -> display messages
+const DISPLAY_WRITE_ADDR: u8 = 0x6E; // 0x37 << 1, seeds the request checksum
+const REPLY_CHECKSUM_SEED: u8 = 0x50; // Replies are checksummed against the virtual host address
+
+pub const VCP_BRIGHTNESS: u8 = 0x10;
+const VCP_GET_REQUEST: u8 = 0x01;
+const VCP_GET_REPLY: u8 = 0x02;
+const VCP_SET: u8 = 0x03;
+const VCP_REPLY_LEN: usize = 11;
+
+// DDC/CI 1.1 timing: 40 ms before reading a reply, 50 ms between commands
+const DDC_REPLY_DELAY: Duration = Duration::from_millis(40);
+const DDC_COMMAND_INTERVAL: Duration = Duration::from_millis(50);
+const DDC_RETRIES: usize = 3;
+
+pub const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const EDID_BLOCK_LEN: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VcpValue {
+    pub current: u16,
+    pub max: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdidInfo {
+    pub manufacturer: String, // PNP ID, e.g. "DEL"
+    pub product: u16,
+    pub serial: u32,
+    pub name: Option<String>, // Monitor name descriptor (0xFC)
+}
+
+fn xor_checksum(seed: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(seed, |acc, b| acc ^ b)
+}
+
+pub fn get_vcp_request(code: u8) -> [u8; 5] {
+    let mut packet = [HOST_ADDR, 0x80 | 2, VCP_GET_REQUEST, code, 0];
+    packet[4] = xor_checksum(DISPLAY_WRITE_ADDR, &packet[..4]);
+    packet
+}
+
+pub fn set_vcp_request(code: u8, value: u16) -> [u8; 7] {
+    let [hi, lo] = value.to_be_bytes();
+    let mut packet = [HOST_ADDR, 0x80 | 4, VCP_SET, code, hi, lo, 0];
+    packet[6] = xor_checksum(DISPLAY_WRITE_ADDR, &packet[..6]);
+    packet
+}
+
+// Reply layout: src, 0x80|len, 0x02, result, code, type, max_hi, max_lo, cur_hi, cur_lo, checksum
+pub fn parse_vcp_reply(code: u8, reply: &[u8]) -> Result<VcpValue, HardwareError> {
+    if reply.len() < 3 {
+        return Err(HardwareError::Protocol("short VCP reply".to_string()));
+    }
+    let len = (reply[1] & 0x7F) as usize;
+    if len == 0 {
+        // Null message: the display is busy, ask again
+        return Err(HardwareError::Protocol("display busy (null reply)".to_string()));
+    }
+    if reply.len() < VCP_REPLY_LEN || len != VCP_REPLY_LEN - 3 {
+        return Err(HardwareError::Protocol(format!("unexpected VCP reply length {}", len)));
+    }
+    let checksum = xor_checksum(REPLY_CHECKSUM_SEED, &reply[..VCP_REPLY_LEN - 1]);
+    if checksum != reply[VCP_REPLY_LEN - 1] {
+        return Err(HardwareError::Protocol(format!(
+            "VCP reply checksum {:#04x} != {:#04x}", reply[VCP_REPLY_LEN - 1], checksum
+        )));
+    }
+    if reply[2] != VCP_GET_REPLY || reply[4] != code {
+        return Err(HardwareError::Protocol(format!("reply for opcode {:#04x} / VCP {:#04x}", reply[2], reply[4])));
+    }
+    if reply[3] != 0 {
+        return Err(HardwareError::NotSupported);
+    }
+    Ok(VcpValue {
+        max: u16::from_be_bytes([reply[6], reply[7]]),
+        current: u16::from_be_bytes([reply[8], reply[9]]),
+    })
+}
+
+pub fn parse_edid(edid: &[u8]) -> Result<EdidInfo, HardwareError> {
+    if edid.len() < EDID_BLOCK_LEN || edid[..8] != EDID_HEADER {
+        return Err(HardwareError::Protocol("missing EDID header".to_string()));
+    }
+    let sum = edid[..EDID_BLOCK_LEN].iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    if sum != 0 {
+        return Err(HardwareError::Protocol("EDID checksum mismatch".to_string()));
+    }
+
+    // Three 5-bit letters, 1 = 'A'
+    let id = u16::from_be_bytes([edid[8], edid[9]]);
+    let manufacturer: String = [10, 5, 0]
+        .iter()
+        .map(|shift| (b'A' - 1 + ((id >> shift) & 0x1F) as u8) as char)
+        .collect();
+
+    let name = (0..4)
+        .map(|i| &edid[54 + i * 18..72 + i * 18])
+        .find(|d| d[..3] == [0, 0, 0] && d[3] == 0xFC)
+        .map(|d| {
+            let text: Vec<u8> = d[5..].iter().copied().take_while(|&b| b != 0x0A).collect();
+            String::from_utf8_lossy(&text).trim().to_string()
+        });
+
+    Ok(EdidInfo {
+        manufacturer,
+        product: u16::from_le_bytes([edid[10], edid[11]]),
+        serial: u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]),
+        name,
+    })
+}
+
+// One /dev/i2c-N adapter. Tracks the last transaction so every command keeps
+// the DDC/CI inter-command delay, whoever issues it.
+pub struct I2cBus {
+    path: PathBuf,
+    file: File,
+    last_command: Option<Instant>,
+}
+
+impl I2cBus {
+    pub fn open(path: &Path) -> Result<Self, HardwareError> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self { path: path.to_path_buf(), file, last_command: None })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn set_slave(&mut self, addr: u16) -> Result<(), HardwareError> {
+        // SAFETY: I2C_SLAVE takes the 7-bit address by value and only touches the fd
+        let rc = unsafe { libc::ioctl(self.file.as_raw_fd(), I2C_SLAVE, libc::c_ulong::from(addr)) };
+        if rc < 0 {
+            return Err(HardwareError::Io(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn wait_for_bus(&self) {
+        if let Some(last) = self.last_command {
+            let elapsed = last.elapsed();
+            if elapsed < DDC_COMMAND_INTERVAL {
+                thread::sleep(DDC_COMMAND_INTERVAL - elapsed);
+            }
+        }
+    }
+
+    pub fn read_edid(&mut self) -> Result<EdidInfo, HardwareError> {
+        self.set_slave(EDID_ADDR)?;
+        self.file.write_all(&[0x00])?;
+        let mut edid = [0u8; EDID_BLOCK_LEN];
+        self.file.read_exact(&mut edid)?;
+        parse_edid(&edid)
+    }
+
+    fn try_get_vcp(&mut self, code: u8) -> Result<VcpValue, HardwareError> {
+        self.wait_for_bus();
+        self.set_slave(DDC_CI_ADDR)?;
+        self.file.write_all(&get_vcp_request(code))?;
+        thread::sleep(DDC_REPLY_DELAY);
+        let mut reply = [0u8; VCP_REPLY_LEN];
+        let result = self.file.read_exact(&mut reply);
+        self.last_command = Some(Instant::now());
+        result?;
+        parse_vcp_reply(code, &reply)
+    }
+
+    fn try_set_vcp(&mut self, code: u8, value: u16) -> Result<(), HardwareError> {
+        self.wait_for_bus();
+        self.set_slave(DDC_CI_ADDR)?;
+        let result = self.file.write_all(&set_vcp_request(code, value));
+        self.last_command = Some(Instant::now());
+        Ok(result?)
+    }
+
+    pub fn get_vcp(&mut self, code: u8) -> Result<VcpValue, HardwareError> {
+        let mut last_err = HardwareError::NotSupported;
+        for _ in 0..DDC_RETRIES {
+            match self.try_get_vcp(code) {
+                Ok(v) => return Ok(v),
+                Err(HardwareError::NotSupported) => return Err(HardwareError::NotSupported),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    pub fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), HardwareError> {
+        let mut last_err = HardwareError::NotSupported;
+        for _ in 0..DDC_RETRIES {
+            match self.try_set_vcp(code, value) {
+                Ok(()) => return Ok(()),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DdcDisplay {
+    pub bus: PathBuf,
+    pub edid: EdidInfo,
+    pub brightness: VcpValue,
+}
+
+// Adapters that are never a monitor's DDC lines; 0x50 on an SMBus is usually
+// a memory module's SPD EEPROM (the list ddcutil skips)
+const NON_DISPLAY_ADAPTERS: [&str; 7] = ["smbus", "synopsys designware", "soc:i2cdsi", "smu", "mac-io", "u4", "amdgpu smu"];
+
+pub fn is_display_adapter(name: &str) -> bool {
+    let name = name.trim().to_ascii_lowercase();
+    !NON_DISPLAY_ADAPTERS.iter().any(|prefix| name.starts_with(prefix))
+}
+
+// The DDC buses of connected outputs as sysfs ties them to their connector, or,
+// for drivers that do not, every adapter whose name is not known to be something else
+pub fn display_buses(sysfs: &SysfsRoot) -> Vec<String> {
+    let mut buses: Vec<String> = sysfs.drm_connectors().into_iter()
+        .filter(|c| c.connected)
+        .filter_map(|c| c.ddc_bus)
+        .collect();
+    if buses.is_empty() {
+        buses = fs::read_dir(sysfs.class("i2c-dev"))
+            .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect())
+            .unwrap_or_default();
+        let adapters = sysfs.path().join("bus").join("i2c").join("devices");
+        buses.retain(|bus| fs::read_to_string(adapters.join(bus).join("name")).is_ok_and(|name| is_display_adapter(&name)));
+    }
+    buses.sort();
+    buses.dedup();
+    buses
+}
+
+// Probes the display adapters' /dev/i2c-N for DDC/CI brightness at 0x37.
+pub fn discover(sysfs: &SysfsRoot) -> Vec<DdcDisplay> {
+    display_buses(sysfs).iter().filter_map(|bus| probe(&Path::new("/dev").join(bus))).collect()
+}
+
+// Only buses with a valid EDID at 0x50 carry a monitor; discover only hands
+// this display adapters, other buses are never written to
+pub fn probe(path: &Path) -> Option<DdcDisplay> {
+    let mut bus = I2cBus::open(path).ok()?;
+    let edid = bus.read_edid().ok()?;
+    let brightness = bus.get_vcp(VCP_BRIGHTNESS).ok()?;
+    let label = edid.name.clone().unwrap_or_else(|| "unknown monitor".to_string());
+    info!("Found DDC/CI display '{}' on {}", label, path.display());
+    Some(DdcDisplay { bus: path.to_path_buf(), edid, brightness })
+}
+
+// Native DDC/CI backend. Writes go to a worker thread that only sends the
+// newest value once the bus is free, so the 125Hz loop never blocks on the
+// monitor's 50ms command interval.
+pub struct DdcController {
+    bus: Arc<Mutex<I2cBus>>,
+    max: u16,
+    writes: mpsc::Sender<u16>,
+    latency: Arc<Mutex<Option<Duration>>>, // Bus time of the worker's last write
+    error: Arc<Mutex<Option<String>>>,     // Why the worker's last write failed, cleared once one lands
+}
+
+impl DdcController {
+    pub fn new(display: &DdcDisplay) -> Result<Self, HardwareError> {
+        let bus = Arc::new(Mutex::new(I2cBus::open(&display.bus)?));
+        let max = display.brightness.max.max(1);
+        let (writes, rx) = mpsc::channel::<u16>();
+
+        let latency = Arc::new(Mutex::new(None));
+        let error = Arc::new(Mutex::new(None));
+        let worker_bus = bus.clone();
+        let worker_latency = latency.clone();
+        let worker_error = error.clone();
+        thread::spawn(move || {
+            let mut written: Option<u16> = None;
+            while let Ok(mut value) = rx.recv() {
+                // Coalesce: only the newest queued value matters
+                while let Ok(newer) = rx.try_recv() {
+                    value = newer;
+                }
+                if written == Some(value) {
+                    continue;
+                }
+                let mut bus = match worker_bus.lock() {
+                    Ok(b) => b,
+                    Err(_) => return,
+                };
+                let started = Instant::now();
+                let failure = match bus.set_vcp(VCP_BRIGHTNESS, value) {
+                    Ok(()) => {
+                        written = Some(value);
+                        None
+                    }
+                    Err(e) => {
+                        warn!("DDC/CI write to {} failed: {}", bus.path().display(), e);
+                        Some(e.to_string())
+                    }
+                };
+                if let Ok(mut latency) = worker_latency.lock() {
+                    *latency = Some(started.elapsed());
+                }
+                if let Ok(mut error) = worker_error.lock() {
+                    *error = failure;
+                }
+            }
+        });
+
+        Ok(Self { bus, max, writes, latency, error })
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.error.lock().ok().and_then(|e| e.clone())
+    }
+
+    pub fn auto() -> Result<Self, HardwareError> {
+        let display = discover(&SysfsRoot::default()).into_iter().next().ok_or(HardwareError::NotSupported)?;
+        Self::new(&display)
+    }
+}
+
+impl BrightnessController for DdcController {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        let mut bus = self.bus.lock().map_err(|_| HardwareError::CommandFailed("Mutex Poisoned".into()))?;
+        let value = bus.get_vcp(VCP_BRIGHTNESS)?;
+        Ok(value.current as f64 / value.max.max(1) as f64 * 100.0)
+    }
+
+    // Queues the value; a failure of an earlier queued write is reported here,
+    // so failover sees a monitor that stopped answering
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        let raw = (value.clamp(0.0, 100.0) / 100.0 * self.max as f64).round() as u16;
+        self.writes.send(raw).map_err(|_| HardwareError::CommandFailed("DDC/CI worker stopped".into()))?;
+        match self.last_error() {
+            Some(e) => Err(HardwareError::CommandFailed(e)),
+            None => Ok(()),
+        }
+    }
+
+    fn status(&self) -> Option<BackendStatus> {
+        Some(BackendStatus {
+            display: String::new(),
+            backend: self.name().to_string(),
+            reason: match self.last_error() {
+                Some(e) => format!("last write failed ({})", e),
+                None => "only backend".to_string(),
+            },
+            failures: 0,
+            standby: Vec::new(),
+        })
+    }
+
+    fn write_latency(&self) -> Option<Duration> {
+        self.latency.lock().ok().and_then(|l| *l)
+    }
+
+    fn name(&self) -> &str {
+        "DDC/CI (native i2c)"
+    }
+
+    // Bounded by the 50 ms DDC/CI command interval
+    fn max_update_hz(&self) -> f64 {
+        1.0 / DDC_COMMAND_INTERVAL.as_secs_f64()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: true,
+            resolution: self.max as u32 + 1,
+            latency: DDC_COMMAND_INTERVAL,
+            shows_osd: false,
+            privilege: Privilege::I2cGroup,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::ddc::{display_buses, get_vcp_request, is_display_adapter, parse_edid, parse_vcp_reply, set_vcp_request, EDID_HEADER, VCP_BRIGHTNESS};
+    use crate::hardware::HardwareError;
+    use crate::sysfs::SysfsRoot;
+    use std::fs;
+
+    #[test]
+    fn test_vcp_request_packets() {
+        assert_eq!(get_vcp_request(VCP_BRIGHTNESS), [0x51, 0x82, 0x01, 0x10, 0xAC]);
+        assert_eq!(set_vcp_request(VCP_BRIGHTNESS, 50), [0x51, 0x84, 0x03, 0x10, 0x00, 0x32, 0x9A]);
+    }
+
+    #[test]
+    fn test_vcp_reply_parsing() {
+        // Brightness 50 of 100
+        let reply = [0x6E, 0x88, 0x02, 0x00, 0x10, 0x00, 0x00, 0x64, 0x00, 0x32, 0xF2];
+        let value = parse_vcp_reply(VCP_BRIGHTNESS, &reply).unwrap();
+        assert_eq!(value.current, 50);
+        assert_eq!(value.max, 100);
+
+        let mut corrupt = reply;
+        corrupt[9] = 0x33;
+        assert!(matches!(parse_vcp_reply(VCP_BRIGHTNESS, &corrupt), Err(HardwareError::Protocol(_))));
+
+        // Null message while the display is busy
+        assert!(matches!(parse_vcp_reply(VCP_BRIGHTNESS, &[0x6E, 0x80, 0xBE]), Err(HardwareError::Protocol(_))));
+
+        // Result code 1: feature unsupported
+        let mut unsupported = reply;
+        unsupported[3] = 0x01;
+        unsupported[10] ^= 0x01;
+        assert!(matches!(parse_vcp_reply(VCP_BRIGHTNESS, &unsupported), Err(HardwareError::NotSupported)));
+    }
+
+    #[test]
+    fn test_edid_parsing() {
+        let mut edid = [0u8; 128];
+        edid[..8].copy_from_slice(&EDID_HEADER);
+        edid[8..10].copy_from_slice(&[0x10, 0xAC]); // "DEL"
+        edid[10..12].copy_from_slice(&0xA0C4u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&12345u32.to_le_bytes());
+        // Second descriptor: monitor name
+        edid[72..77].copy_from_slice(&[0, 0, 0, 0xFC, 0]);
+        edid[77..90].copy_from_slice(b"DELL U2720Q\n ");
+        let sum = edid[..127].iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        edid[127] = 0u8.wrapping_sub(sum);
+
+        let info = parse_edid(&edid).unwrap();
+        assert_eq!(info.manufacturer, "DEL");
+        assert_eq!(info.product, 0xA0C4);
+        assert_eq!(info.serial, 12345);
+        assert_eq!(info.name.as_deref(), Some("DELL U2720Q"));
+
+        edid[127] = edid[127].wrapping_add(1);
+        assert!(parse_edid(&edid).is_err());
+    }
+
+    #[test]
+    fn test_display_buses() {
+        assert!(is_display_adapter("i915 gmbus dpc"));
+        assert!(is_display_adapter("AMDGPU DM i2c hw bus 1"));
+        assert!(!is_display_adapter("SMBus I801 adapter at efa0"));
+        assert!(!is_display_adapter("Synopsys DesignWare I2C adapter"));
+
+        let root = SysfsRoot::new(std::env::temp_dir().join(format!("epilyzer-ddc-{}", std::process::id())));
+        let adapters = root.path().join("bus").join("i2c").join("devices");
+        for (bus, name) in [("i2c-0", "SMBus I801 adapter at efa0"), ("i2c-4", "i915 gmbus dpb")] {
+            fs::create_dir_all(root.class("i2c-dev").join(bus)).unwrap();
+            fs::create_dir_all(adapters.join(bus)).unwrap();
+            fs::write(adapters.join(bus).join("name"), format!("{}\n", name)).unwrap();
+        }
+        // No connector names its bus: fall back to the adapter names
+        assert_eq!(display_buses(&root), vec!["i2c-4".to_string()]);
+
+        // Connectors that do name it are all that is probed
+        let connector = root.class("drm").join("card0-DP-1");
+        fs::create_dir_all(connector.join("i2c-7")).unwrap();
+        fs::write(connector.join("status"), "connected\n").unwrap();
+        assert_eq!(display_buses(&root), vec!["i2c-7".to_string()]);
+        fs::remove_dir_all(root.path()).ok();
+    }
+}
+
@@ -1,19 +1,235 @@
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
+use crate::clock::{system_clock, SharedClock};
 
 pub const MIN_TRANSITION_TIME_SEC: f64 = 2.0; 
 pub const MAX_CHANGE_FREQUENCY_HZ: f64 = 3.0;
 pub const MIN_SAFE_INTERVAL_MS: u128 = (1000.0 / MAX_CHANGE_FREQUENCY_HZ) as u128;
 pub const MAX_DELTA_PER_STEP: f64 = 2.0;
 pub const RED_FLASH_THRESHOLD: f64 = 0.8;
+// First transition after an emergency stop ends is stretched to at least this long
+pub const RESUME_RAMP_MS: u64 = 5000;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+// WCAG 2.3.1 / ITU-R BT.1702 general flash definition
+pub const FLASH_LUMINANCE_DELTA: f64 = 0.1;
+pub const FLASH_DARK_LUMINANCE_LIMIT: f64 = 0.8;
+pub const MAX_FLASHES_PER_SECOND: f64 = 3.0;
+
+// WCAG 2.3.1 red flash definition: a saturated red has R / (R + G + B) >= RED_FLASH_THRESHOLD,
+// and a red transition is a change of more than 20 in (R - G - B) * 320
+pub const RED_FLASH_SCALE: f64 = 320.0;
+pub const RED_FLASH_DELTA: f64 = 20.0;
+
+// Flashes only count when they cover more than 25% of any 10 degree visual field
+pub const FLASH_VISUAL_FIELD_DEG: f64 = 10.0;
+pub const FLASH_AREA_FRACTION: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum SafetyMode {
+    #[default]
     Automatic,
+    Safe,  // Capped at safe_mode_brightness, slow transitions, sensitive flashbang
+    Sleep, // Night use: dim cap, very slow transitions, most sensitive flashbang
     EmergencyStop,
 }
 
+// Per-mode limits layered on top of the WCAG constants above
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeProfile {
+    pub brightness_cap: f64,
+    pub min_transition_ms: u64,    // Floor for every non-flashbang transition
+    pub flashbang_threshold: f64,  // Screen intensity (0.0 - 1.0) where content dimming starts
+}
+
+pub const DEFAULT_SAFE_MODE_BRIGHTNESS: f64 = 40.0;
+pub const DEFAULT_SLEEP_MODE_BRIGHTNESS: f64 = 20.0;
+
+impl SafetyMode {
+    pub fn profile(&self, safe_cap: f64, sleep_cap: f64) -> ModeProfile {
+        match self {
+            SafetyMode::Automatic => ModeProfile { brightness_cap: 100.0, min_transition_ms: 300, flashbang_threshold: 0.5 },
+            SafetyMode::Safe => ModeProfile { brightness_cap: safe_cap, min_transition_ms: 2000, flashbang_threshold: 0.35 },
+            SafetyMode::Sleep => ModeProfile { brightness_cap: sleep_cap, min_transition_ms: 5000, flashbang_threshold: 0.2 },
+            SafetyMode::EmergencyStop => ModeProfile { brightness_cap: 0.0, min_transition_ms: RESUME_RAMP_MS, flashbang_threshold: 0.0 },
+        }
+    }
+}
+
+impl std::str::FromStr for SafetyMode {
+    type Err = String;
+
+    // Accepts the GeneralConfig.mode names
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "normal" | "automatic" | "auto" => Ok(SafetyMode::Automatic),
+            "safe" => Ok(SafetyMode::Safe),
+            "sleep" => Ok(SafetyMode::Sleep),
+            other => Err(format!("Unknown mode '{}' (expected normal, safe or sleep)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Easing {
+    #[default]
+    Sine, // Half cosine, the original curve
+    Linear,
+    Cubic,
+    Quintic,
+    Exponential,
+    Perceptual, // Half cosine in CIE L* space: equal steps of perceived lightness
+}
+
+impl Easing {
+    pub const ALL: [Easing; 6] = [
+        Easing::Sine,
+        Easing::Linear,
+        Easing::Cubic,
+        Easing::Quintic,
+        Easing::Exponential,
+        Easing::Perceptual,
+    ];
+
+    // Maps progress t (0.0 - 1.0) to eased progress, always 0 at t=0 and 1 at t=1
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Sine | Easing::Perceptual => -((std::f64::consts::PI * t).cos() - 1.0) / 2.0,
+            Easing::Linear => t,
+            Easing::Cubic => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::Quintic => {
+                if t < 0.5 {
+                    16.0 * t.powi(5)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(5) / 2.0
+                }
+            }
+            Easing::Exponential => {
+                if t <= 0.0 {
+                    0.0
+                } else if t >= 1.0 {
+                    1.0
+                } else if t < 0.5 {
+                    2f64.powf(20.0 * t - 10.0) / 2.0
+                } else {
+                    (2.0 - 2f64.powf(-20.0 * t + 10.0)) / 2.0
+                }
+            }
+        }
+    }
+
+    // Steepest point of the curve relative to a linear ramp (1.0 = linear),
+    // sampled so it covers every variant
+    pub fn max_slope(&self) -> f64 {
+        const SAMPLES: usize = 200;
+        (0..SAMPLES)
+            .map(|i| {
+                let t0 = i as f64 / SAMPLES as f64;
+                let t1 = (i + 1) as f64 / SAMPLES as f64;
+                (self.apply(t1) - self.apply(t0)) * SAMPLES as f64
+            })
+            .fold(1.0, f64::max)
+    }
+
+    // Backlight percent in and out; in Perceptual space the curve runs over L*
+    // (where the Perceptual easing is a plain half cosine)
+    pub fn interpolate_in(&self, space: BrightnessSpace, from: f64, to: f64, t: f64) -> f64 {
+        match (self, space) {
+            (_, BrightnessSpace::Linear) => self.interpolate(from, to, t),
+            (Easing::Perceptual, _) => Easing::Sine.interpolate_in(space, from, to, t),
+            _ => space.to_percent(self.interpolate(space.from_percent(from), space.from_percent(to), t)),
+        }
+    }
+
+    pub fn interpolate(&self, from: f64, to: f64, t: f64) -> f64 {
+        let eased = self.apply(t);
+        match self {
+            Easing::Perceptual => {
+                let l_from = lightness_from_percent(from);
+                let l_to = lightness_from_percent(to);
+                percent_from_lightness(l_from + (l_to - l_from) * eased)
+            }
+            _ => from + (to - from) * eased,
+        }
+    }
+}
+
+impl std::str::FromStr for Easing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sine" => Ok(Easing::Sine),
+            "linear" => Ok(Easing::Linear),
+            "cubic" => Ok(Easing::Cubic),
+            "quintic" => Ok(Easing::Quintic),
+            "exponential" => Ok(Easing::Exponential),
+            "perceptual" => Ok(Easing::Perceptual),
+            other => Err(format!("Unknown easing '{}' (expected sine, linear, cubic, quintic, exponential or perceptual)", other)),
+        }
+    }
+}
+
+// Where EpilepsyGuard interpolates transitions and limits its steps. Its values
+// (and every configured, saved and IPC value) stay backlight percent either way;
+// in Perceptual space they are converted to CIE L* inside the guard, so
+// MAX_DELTA_PER_STEP is perceived change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrightnessSpace {
+    Linear,
+    #[default]
+    Perceptual,
+}
+
+impl BrightnessSpace {
+    pub fn to_percent(&self, value: f64) -> f64 {
+        match self {
+            BrightnessSpace::Linear => value,
+            BrightnessSpace::Perceptual => percent_from_lightness(value),
+        }
+    }
+
+    pub fn from_percent(&self, percent: f64) -> f64 {
+        match self {
+            BrightnessSpace::Linear => percent,
+            BrightnessSpace::Perceptual => lightness_from_percent(percent),
+        }
+    }
+}
+
+// CIE 1976 L* (0 - 100) for a luminance given as percent of maximum
+const CIE_EPSILON: f64 = 216.0 / 24389.0;
+const CIE_KAPPA: f64 = 24389.0 / 27.0;
+
+pub fn lightness_from_percent(percent: f64) -> f64 {
+    let y = (percent / 100.0).clamp(0.0, 1.0);
+    if y > CIE_EPSILON {
+        116.0 * y.cbrt() - 16.0
+    } else {
+        CIE_KAPPA * y
+    }
+}
+
+pub fn percent_from_lightness(lightness: f64) -> f64 {
+    let l = lightness.clamp(0.0, 100.0);
+    let y = if l > CIE_KAPPA * CIE_EPSILON {
+        ((l + 16.0) / 116.0).powi(3)
+    } else {
+        l / CIE_KAPPA
+    };
+    y * 100.0
+}
+
 #[derive(Debug, Clone)]
 pub struct TransitionState {
     pub current_brightness: f64,
@@ -21,6 +237,7 @@ pub struct TransitionState {
     pub start_time: Instant,
     pub duration: Duration,
     pub initial_brightness: f64,
+    pub easing: Easing,
 }
 
 pub struct EpilepsyGuard {
@@ -31,21 +248,62 @@ pub struct EpilepsyGuard {
     pub last_user_override: Option<Instant>,
     pub is_locked: bool,
     pub transition_duration_ms: u64,
+    pub easing: Easing,
+    pub fast_easing: Easing, // Used by force_instant_transition (flashbang dimming)
+    pub space: BrightnessSpace,
+    pub freeze_until: Option<Instant>, // None while stopped = until explicitly resumed
+    pub pending_ramp_ms: Option<u64>, // Duration floor for the next transition (resume / mode change)
+    pub safe_mode_brightness: f64,
+    pub sleep_mode_brightness: f64,
+    mode_before_stop: SafetyMode,
+    clock: SharedClock,
 }
 
 impl EpilepsyGuard {
     pub fn new(initial_brightness: f64) -> Self {
+        Self::with_clock(initial_brightness, system_clock())
+    }
+
+    pub fn with_clock(initial_brightness: f64, clock: SharedClock) -> Self {
         Self {
             mode: SafetyMode::Automatic,
-            last_change_time: Instant::now(),
+            last_change_time: clock.now(),
             current_brightness: initial_brightness,
             transition: None,
             last_user_override: None,
             is_locked: false,
             transition_duration_ms: 750, // Default
+            easing: Easing::default(),
+            fast_easing: Easing::default(),
+            space: BrightnessSpace::default(),
+            freeze_until: None,
+            pending_ramp_ms: None,
+            safe_mode_brightness: DEFAULT_SAFE_MODE_BRIGHTNESS,
+            sleep_mode_brightness: DEFAULT_SLEEP_MODE_BRIGHTNESS,
+            mode_before_stop: SafetyMode::Automatic,
+            clock,
         }
     }
 
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    // Linear for guards whose values are not light output (kelvin, keyboard levels)
+    pub fn set_space(&mut self, space: BrightnessSpace) {
+        self.space = space;
+    }
+
+    pub fn set_easing(&mut self, easing: Easing) {
+        self.easing = easing;
+        info!("Transition easing set to {:?}", easing);
+    }
+
+    pub fn set_fast_easing(&mut self, easing: Easing) {
+        self.fast_easing = easing;
+        info!("Fast transition easing set to {:?}", easing);
+    }
+
     pub fn set_transition_duration(&mut self, ms: u64) {
         // Clamp to safe range: 300ms (still fast) to 2000ms (very slow)
         self.transition_duration_ms = ms.clamp(300, 2000);
@@ -53,19 +311,106 @@ impl EpilepsyGuard {
     }
 
     pub fn set_user_override(&mut self) {
-        self.last_user_override = Some(Instant::now());
+        self.last_user_override = Some(self.clock.now());
     }
 
-    pub fn get_safety_cap(&self) -> f64 {
-        match self.mode {
-            SafetyMode::EmergencyStop => 0.0,
-            _ => 100.0,
+    pub fn set_mode_caps(&mut self, safe: f64, sleep: f64) {
+        self.safe_mode_brightness = safe;
+        self.sleep_mode_brightness = sleep;
+    }
+
+    pub fn profile(&self) -> ModeProfile {
+        self.mode.profile(self.safe_mode_brightness, self.sleep_mode_brightness)
+    }
+
+    // Switches between Automatic, Safe and Sleep. The change itself is a guarded
+    // transition: brightness above the new cap is ramped down using the slower
+    // of the two modes' floors, and the next transition after leaving a slow
+    // mode is held to the same floor so the screen never jumps up. While stopped
+    // only the mode to resume into changes; lifting the stop takes a Resume.
+    pub fn set_mode(&mut self, mode: SafetyMode) {
+        if mode == SafetyMode::EmergencyStop {
+            self.emergency_stop(None);
+            return;
+        }
+        if self.mode == SafetyMode::EmergencyStop {
+            info!("Safety mode {:?} applies once the emergency stop is lifted", mode);
+            self.mode_before_stop = mode;
+            return;
+        }
+        if self.mode == mode {
+            return;
+        }
+
+        let ramp_ms = self.profile().min_transition_ms.max(mode.profile(self.safe_mode_brightness, self.sleep_mode_brightness).min_transition_ms);
+        info!("Safety mode {:?} -> {:?}", self.mode, mode);
+        self.mode = mode;
+        self.pending_ramp_ms = Some(ramp_ms);
+
+        let cap = self.get_safety_cap();
+        let heading_to = self.transition.as_ref().map_or(self.current_brightness, |t| t.target_brightness);
+        if self.current_brightness > cap || heading_to > cap {
+            self.transition = None;
+            self.request_transition(cap);
+        }
+    }
+
+    pub fn emergency_stop(&mut self, duration: Option<Duration>) {
+        if self.mode != SafetyMode::EmergencyStop {
+            self.mode_before_stop = self.mode;
+        }
+        self.mode = SafetyMode::EmergencyStop;
+        self.transition = None;
+        self.freeze_until = duration.map(|d| self.clock.now() + d);
+        match duration {
+            Some(d) => warn!("EMERGENCY STOP for {}s", d.as_secs()),
+            None => warn!("EMERGENCY STOP until resumed"),
+        }
+    }
+
+    // Emergency stop that still finishes a fast dim to `target`. The mode changes
+    // first, so nothing else can start a transition while the screen dims.
+    pub fn emergency_stop_dimmed(&mut self, target: f64, duration: Option<Duration>) {
+        self.emergency_stop(duration);
+        let target = Self::clamp_safe(target);
+        if target < self.current_brightness - 0.1 {
+            self.transition = Some(TransitionState {
+                current_brightness: self.current_brightness,
+                initial_brightness: self.current_brightness,
+                target_brightness: target,
+                start_time: self.clock.now(),
+                duration: Duration::from_millis(200),
+                easing: self.fast_easing,
+            });
         }
     }
 
+    // Leaves EmergencyStop for the mode active before it; the next transition is
+    // a slow ramp so the screen never jumps back to the automatic target
+    pub fn resume(&mut self) {
+        if self.mode != SafetyMode::EmergencyStop {
+            return;
+        }
+        self.mode = self.mode_before_stop;
+        self.freeze_until = None;
+        self.pending_ramp_ms = Some(RESUME_RAMP_MS.max(self.profile().min_transition_ms));
+        info!("Emergency stop lifted, resuming {:?} mode", self.mode);
+    }
+
+    pub fn freeze_remaining(&self) -> Option<Duration> {
+        if self.mode != SafetyMode::EmergencyStop {
+            return None;
+        }
+        self.freeze_until.map(|until| until.saturating_duration_since(self.clock.now()))
+    }
+
+    pub fn get_safety_cap(&self) -> f64 {
+        self.profile().brightness_cap
+    }
+
     pub fn is_in_grace_period(&self, duration: Duration) -> bool {
         if let Some(last) = self.last_user_override {
-            last.elapsed() < duration
+            self.clock.now().duration_since(last) < duration
         } else {
             false
         }
@@ -75,7 +420,7 @@ impl EpilepsyGuard {
         if self.mode == SafetyMode::EmergencyStop {
             return false;
         }
-        let elapsed = self.last_change_time.elapsed().as_millis();
+        let elapsed = self.clock.now().duration_since(self.last_change_time).as_millis();
         elapsed >= MIN_SAFE_INTERVAL_MS
     }
 
@@ -83,6 +428,11 @@ impl EpilepsyGuard {
         val.clamp(5.0, 100.0)
     }
 
+    // Distance between two backlight percents as it is perceived, for the 0.1 dead-band
+    fn perceived_distance(&self, a: f64, b: f64) -> f64 {
+        (self.space.from_percent(a) - self.space.from_percent(b)).abs()
+    }
+
     pub fn calculate_next_step(&mut self, target: f64) -> f64 {
          if self.mode == SafetyMode::EmergencyStop {
             return self.current_brightness;
@@ -91,19 +441,21 @@ impl EpilepsyGuard {
         let cap = self.get_safety_cap();
         let target = Self::clamp_safe(target.min(cap));
 
-        if (self.current_brightness - target).abs() < 0.1 {
+        if self.perceived_distance(self.current_brightness, target) < 0.1 {
              return self.current_brightness;
         }
 
         let max_change = MAX_DELTA_PER_STEP;
         
-        let diff = target - self.current_brightness;
+        // The step limit is perceived change in Perceptual space
+        let current = self.space.from_percent(self.current_brightness);
+        let diff = self.space.from_percent(target) - current;
         let step = diff.clamp(-max_change, max_change);
         
-        let new_brightness = Self::clamp_safe(self.current_brightness + step);
+        let new_brightness = Self::clamp_safe(self.space.to_percent(current + step));
         
         self.current_brightness = new_brightness;
-        self.last_change_time = Instant::now();
+        self.last_change_time = self.clock.now();
 
         new_brightness
     }
@@ -119,42 +471,77 @@ impl EpilepsyGuard {
 
         // If we are already transitioning to this target, do nothing
         if let Some(ref trans) = self.transition {
-            if (trans.target_brightness - target).abs() < 0.1 {
+            if self.perceived_distance(trans.target_brightness, target) < 0.1 {
                 return;
             }
         }
 
-        let distance = (target - self.current_brightness).abs();
-        if distance < 0.1 {
+        if self.perceived_distance(target, self.current_brightness) < 0.1 {
             return;
         }
 
         // Use configurable transition duration (epilepsy-safe, WCAG compliant)
+        let mut duration_ms = self.transition_duration_ms.max(self.profile().min_transition_ms);
+        if let Some(ramp_ms) = self.pending_ramp_ms.take() {
+            duration_ms = duration_ms.max(ramp_ms);
+        }
         self.transition = Some(TransitionState {
             current_brightness: self.current_brightness,
             initial_brightness: self.current_brightness,
             target_brightness: target,
-            start_time: Instant::now(),
-            duration: Duration::from_millis(self.transition_duration_ms),
+            start_time: self.clock.now(),
+            duration: Duration::from_millis(duration_ms),
+            easing: self.easing,
         });
         
         info!("Transition started: {:.1} -> {:.1} ({}ms)", 
-              self.current_brightness, target, self.transition_duration_ms);
+              self.current_brightness, target, duration_ms);
+    }
+
+    // Transition with explicit timing, used by per-display guards following the
+    // master guard (which already applied the mode floors). Caps still apply;
+    // while stopped only a dimming transition is accepted.
+    pub fn request_transition_with(&mut self, target: f64, duration: Duration, easing: Easing) {
+        let target = if self.mode == SafetyMode::EmergencyStop {
+            if target >= self.current_brightness - 0.1 {
+                return;
+            }
+            Self::clamp_safe(target)
+        } else {
+            Self::clamp_safe(target.min(self.get_safety_cap()))
+        };
+
+        if self.perceived_distance(target, self.current_brightness) < 0.1 {
+            self.transition = None;
+            return;
+        }
+
+        self.transition = Some(TransitionState {
+            current_brightness: self.current_brightness,
+            initial_brightness: self.current_brightness,
+            target_brightness: target,
+            start_time: self.clock.now(),
+            duration,
+            easing,
+        });
     }
 
     pub fn force_instant_transition(&mut self, target: f64) {
+        // Frozen means frozen; only emergency_stop_dimmed may still dim
+        if self.mode == SafetyMode::EmergencyStop {
+            return;
+        }
         let cap = self.get_safety_cap();
         let target = target.min(cap);
         
         // If we are already transitioning to this target, do nothing
         if let Some(ref trans) = self.transition {
-            if (trans.target_brightness - target).abs() < 0.1 {
+            if self.perceived_distance(trans.target_brightness, target) < 0.1 {
                 return;
             }
         }
         
-        let distance = (target - self.current_brightness).abs();
-        if distance < 0.1 {
+        if self.perceived_distance(target, self.current_brightness) < 0.1 {
             return;
         }
 
@@ -164,20 +551,27 @@ impl EpilepsyGuard {
             current_brightness: self.current_brightness,
             initial_brightness: self.current_brightness,
             target_brightness: target,
-            start_time: Instant::now(),
+            start_time: self.clock.now(),
             duration: Duration::from_millis(200),
+            easing: self.fast_easing,
         });
         info!("Fast transition started: {:.1} -> {:.1} (200ms)", self.current_brightness, target);
     }
 
     pub fn tick_transition(&mut self) -> Option<f64> {
-        if self.mode == SafetyMode::EmergencyStop {
+        if self.mode == SafetyMode::EmergencyStop && self.freeze_until.is_some_and(|until| self.clock.now() >= until) {
+            self.resume();
+        }
+
+        // Only a dimming transition may finish while stopped
+        let dimming = self.transition.as_ref().is_some_and(|t| t.target_brightness < t.initial_brightness);
+        if self.mode == SafetyMode::EmergencyStop && !dimming {
              self.transition = None;
              return None;
         }
 
         if let Some(ref trans) = self.transition {
-            let elapsed = trans.start_time.elapsed().as_secs_f64();
+            let elapsed = self.clock.now().duration_since(trans.start_time).as_secs_f64();
             let total_dur = trans.duration.as_secs_f64();
             
             if elapsed >= total_dur {
@@ -188,18 +582,250 @@ impl EpilepsyGuard {
             }
 
             let t = elapsed / total_dur;
-            let eased_t = Self::ease_in_out(t);
-            
-            let new_val = trans.initial_brightness + (trans.target_brightness - trans.initial_brightness) * eased_t;
+            let new_val = trans.easing.interpolate_in(self.space, trans.initial_brightness, trans.target_brightness, t);
             
             self.current_brightness = new_val;
-            self.last_change_time = Instant::now();
+            self.last_change_time = self.clock.now();
             return Some(new_val);
         }
         None
     }
     
     pub fn ease_in_out(t: f64) -> f64 {
-        -( (std::f64::consts::PI * t).cos() - 1.0) / 2.0
+        Easing::Sine.apply(t)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlashKind {
+    General,
+    Red,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlashViolation {
+    pub kind: FlashKind,
+    pub timestamp: Duration,
+    pub flashes: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trend {
+    Rising,
+    Falling,
+}
+
+// Counts opposing luminance transitions over a sliding one-second window.
+// Timestamps are offsets from an arbitrary origin (stream start, frame PTS, ...)
+// so the daemon and offline tools can share the same implementation.
+#[derive(Debug, Clone)]
+pub struct FlashDetector {
+    pub kind: FlashKind,
+    min_delta: f64,
+    dark_limit: f64,
+    window: Duration,
+    reference: Option<f64>,
+    trend: Option<Trend>,
+    transitions: VecDeque<Duration>,
+}
+
+impl Default for FlashDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlashDetector {
+    pub fn new() -> Self {
+        Self {
+            kind: FlashKind::General,
+            min_delta: FLASH_LUMINANCE_DELTA,
+            dark_limit: FLASH_DARK_LUMINANCE_LIMIT,
+            window: Duration::from_secs(1),
+            reference: None,
+            trend: None,
+            transitions: VecDeque::new(),
+        }
+    }
+
+    // Takes red_flash_value() samples instead of relative luminance
+    pub fn red() -> Self {
+        Self {
+            kind: FlashKind::Red,
+            min_delta: RED_FLASH_DELTA,
+            dark_limit: f64::INFINITY,
+            ..Self::new()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.reference = None;
+        self.trend = None;
+        self.transitions.clear();
+    }
+
+    // Feed one relative-luminance sample (0.0 - 1.0). Returns a violation when the
+    // window now holds more than MAX_FLASHES_PER_SECOND flashes.
+    pub fn push(&mut self, timestamp: Duration, luminance: f64) -> Option<FlashViolation> {
+        let reference = match self.reference {
+            Some(r) => r,
+            None => {
+                self.reference = Some(luminance);
+                return None;
+            }
+        };
+
+        // Extend the current extreme while moving in the same direction
+        match self.trend {
+            Some(Trend::Rising) if luminance > reference => {
+                self.reference = Some(luminance);
+                return self.evaluate(timestamp);
+            }
+            Some(Trend::Falling) if luminance < reference => {
+                self.reference = Some(luminance);
+                return self.evaluate(timestamp);
+            }
+            _ => {}
+        }
+
+        let delta = luminance - reference;
+        let darker = luminance.min(reference);
+        if delta.abs() >= self.min_delta && darker < self.dark_limit {
+            let direction = if delta > 0.0 { Trend::Rising } else { Trend::Falling };
+            if self.trend != Some(direction) {
+                self.transitions.push_back(timestamp);
+                self.trend = Some(direction);
+            }
+            self.reference = Some(luminance);
+        }
+
+        self.evaluate(timestamp)
+    }
+
+    // A flash is a pair of opposing transitions
+    pub fn flashes_in_window(&self) -> f64 {
+        self.transitions.len() as f64 / 2.0
+    }
+
+    fn evaluate(&mut self, now: Duration) -> Option<FlashViolation> {
+        while let Some(&oldest) = self.transitions.front() {
+            if now.saturating_sub(oldest) > self.window {
+                self.transitions.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let flashes = self.flashes_in_window();
+        if flashes > MAX_FLASHES_PER_SECOND {
+            Some(FlashViolation { kind: self.kind, timestamp: now, flashes })
+        } else {
+            None
+        }
+    }
+}
+
+// Size (px) of the visual field flash area is judged in. It is modelled as the
+// 4:3 rectangle WCAG uses (341 x 256 px on a 1024 x 768 screen).
+pub fn visual_field_px(viewing_distance_cm: f64, dpi: f64) -> (f64, f64) {
+    let half_angle = (FLASH_VISUAL_FIELD_DEG / 2.0).to_radians();
+    let field_width_cm = 2.0 * viewing_distance_cm * half_angle.tan();
+    let field_width_px = field_width_cm / 2.54 * dpi;
+    (field_width_px, field_width_px * 0.75)
+}
+
+// Area (px²) above which simultaneous flashing within one visual field is hazardous
+pub fn hazard_area_px(viewing_distance_cm: f64, dpi: f64) -> f64 {
+    let (width, height) = visual_field_px(viewing_distance_cm, dpi);
+    width * height * FLASH_AREA_FRACTION
+}
+
+// Runs one FlashDetector per cell of a cols x rows grid and only reports a violation
+// when the cells flashing together inside one visual field cover more than
+// FLASH_AREA_FRACTION of it. The field slides over the grid in whole cells, its
+// size rounded to the nearest cell count.
+pub struct AreaFlashDetector {
+    cells: Vec<FlashDetector>,
+    cols: usize,
+    rows: usize,
+    window: (usize, usize),
+    cell_area_px: f64,
+    hazard_area_px: f64,
+}
+
+impl AreaFlashDetector {
+    pub fn new(template: FlashDetector, cols: usize, rows: usize, cell_size_px: (f64, f64), field_px: (f64, f64)) -> Self {
+        let span = |field: f64, cell: f64, cells: usize| ((field / cell).round() as usize).clamp(1, cells.max(1));
+        Self {
+            cells: vec![template; cols * rows],
+            cols,
+            rows,
+            window: (span(field_px.0, cell_size_px.0, cols), span(field_px.1, cell_size_px.1, rows)),
+            cell_area_px: cell_size_px.0 * cell_size_px.1,
+            hazard_area_px: field_px.0 * field_px.1 * FLASH_AREA_FRACTION,
+        }
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    // `samples` holds one value per cell, row-major, in the same order every frame
+    pub fn push(&mut self, timestamp: Duration, samples: &[f64]) -> Option<FlashViolation> {
+        let mut flashing = vec![false; self.cells.len()];
+        let mut worst: Option<FlashViolation> = None;
+
+        for ((detector, &value), flashing) in self.cells.iter_mut().zip(samples).zip(flashing.iter_mut()) {
+            if let Some(v) = detector.push(timestamp, value) {
+                *flashing = true;
+                if worst.is_none_or(|w| v.flashes > w.flashes) {
+                    worst = Some(v);
+                }
+            }
+        }
+        worst?;
+
+        let (window_cols, window_rows) = self.window;
+        let cols = self.cols;
+        for top in 0..=self.rows.saturating_sub(window_rows) {
+            for left in 0..=cols.saturating_sub(window_cols) {
+                let count = (top..top + window_rows)
+                    .flat_map(|row| (left..left + window_cols).map(move |col| row * cols + col))
+                    .filter(|&cell| flashing.get(cell).copied().unwrap_or(false))
+                    .count();
+                if count as f64 * self.cell_area_px > self.hazard_area_px {
+                    return worst;
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// WCAG relative luminance from non-linear sRGB components (0.0 - 1.0)
+pub fn relative_luminance(r: f64, g: f64, b: f64) -> f64 {
+    0.2126 * srgb_to_linear(r) + 0.7152 * srgb_to_linear(g) + 0.0722 * srgb_to_linear(b)
+}
+
+pub fn is_saturated_red(r: f64, g: f64, b: f64) -> bool {
+    let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+    let sum = r + g + b;
+    sum > 0.0 && r / sum >= RED_FLASH_THRESHOLD
+}
+
+// Red flash metric for one pixel: (R - G - B) * 320 for saturated reds, 0 otherwise
+pub fn red_flash_value(r: f64, g: f64, b: f64) -> f64 {
+    if !is_saturated_red(r, g, b) {
+        return 0.0;
     }
+    let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+    ((r - g - b) * RED_FLASH_SCALE).max(0.0)
 }
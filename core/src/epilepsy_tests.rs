@@ -1,8 +1,13 @@
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::epilepsy::{EpilepsyGuard, SafetyMode};
-    use std::thread;
+    use crate::epilepsy::{
+        hazard_area_px, red_flash_value, visual_field_px, AreaFlashDetector, BrightnessSpace, Easing, EpilepsyGuard, FlashDetector, SafetyMode, MAX_DELTA_PER_STEP, MAX_FLASHES_PER_SECOND, RED_FLASH_DELTA, RESUME_RAMP_MS,
+    };
+    use crate::clock::ManualClock;
+    use crate::config::LocationConfig;
+    use crate::context::ContextManager;
+    use chrono::{TimeZone, Utc};
     use std::time::Duration;
 
     #[test]
@@ -14,17 +19,53 @@ mod tests {
 
     #[test]
     fn test_frequency_limit() {
-        let guard = EpilepsyGuard::new(50.0);
+        let clock = ManualClock::shared(Utc::now());
+        let guard = EpilepsyGuard::with_clock(50.0, clock.clone());
         // Initially blocked because last_change_time is now and MIN_SAFE_INTERVAL is ~333ms
         assert!(!guard.can_update()); 
         
-        thread::sleep(Duration::from_millis(350));
+        clock.advance(Duration::from_millis(350));
         assert!(guard.can_update()); // Should be allowed after wait
     }
 
+    #[test]
+    fn test_simulated_day() {
+        // A whole day of circadian updates at 1s resolution, no sleeping
+        let start = Utc.with_ymd_and_hms(2024, 6, 21, 0, 0, 0).unwrap();
+        let clock = ManualClock::shared(start);
+        let config = LocationConfig {
+            latitude: Some(41.0082),
+            longitude: Some(28.9784),
+            method: "manual".to_string(),
+            timezone: "Europe/Istanbul".to_string(),
+        };
+        let ctx = ContextManager::with_clock(&config, "07:00", clock.clone());
+        let mut guard = EpilepsyGuard::with_clock(20.0, clock.clone());
+
+        let mut min_seen = f64::MAX;
+        let mut max_seen = f64::MIN;
+        for _ in 0..(24 * 3600) {
+            clock.advance(Duration::from_secs(1));
+            let target = ctx.get_circadian_target_now();
+            if (guard.current_brightness - target).abs() > 5.0 {
+                guard.request_transition(target);
+            }
+            if let Some(v) = guard.tick_transition() {
+                assert!((0.0..=100.0).contains(&v));
+            }
+            min_seen = min_seen.min(guard.current_brightness);
+            max_seen = max_seen.max(guard.current_brightness);
+        }
+
+        assert_eq!(clock.elapsed(), Duration::from_secs(24 * 3600));
+        assert!(max_seen > 90.0, "midday brightness {}", max_seen);
+        assert!(min_seen < 25.0, "night brightness {}", min_seen);
+    }
+
     #[test]
     fn test_step_limit() {
         let mut guard = EpilepsyGuard::new(50.0);
+        guard.set_space(BrightnessSpace::Linear);
         
         // Try to jump to 100
         let next = guard.calculate_next_step(100.0);
@@ -32,6 +73,30 @@ mod tests {
         // Should only increase by MAX_DELTA_PER_STEP (2.0)
         assert!((next - 52.0).abs() < 0.01);
         assert_eq!(guard.current_brightness, next);
+
+        // By default the limit is 2 L*, still backlight percent in and out
+        let mut guard = EpilepsyGuard::new(50.0);
+        let next = guard.calculate_next_step(100.0);
+        let space = BrightnessSpace::Perceptual;
+        assert!((space.from_percent(next) - space.from_percent(50.0) - MAX_DELTA_PER_STEP).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_dead_band_is_perceived() {
+        // At the dark end 0.08% of backlight is ~0.2 L*, a visible change
+        let space = BrightnessSpace::Perceptual;
+        assert!(space.from_percent(5.08) - space.from_percent(5.0) > 0.1);
+        let mut guard = EpilepsyGuard::new(5.0);
+        guard.request_transition(5.08);
+        assert!(guard.transition.is_some());
+        assert!(guard.calculate_next_step(5.08) > 5.0);
+
+        // In linear space it stays inside the dead-band
+        let mut guard = EpilepsyGuard::new(5.0);
+        guard.set_space(BrightnessSpace::Linear);
+        guard.request_transition(5.08);
+        assert!(guard.transition.is_none());
+        assert_eq!(guard.calculate_next_step(5.08), 5.0);
     }
 
     #[test]
@@ -79,4 +144,262 @@ mod tests {
         assert_eq!(guard.transition.as_ref().unwrap().target_brightness, 100.0);
         assert_eq!(guard.transition.as_ref().unwrap().duration.as_millis(), 200);
     }
+
+    fn feed_square_wave(detector: &mut FlashDetector, hz: f64, low: f64, high: f64, secs: f64) -> bool {
+        // Two samples per period: one at each level
+        let half_period = 1.0 / (hz * 2.0);
+        let mut t = 0.0;
+        let mut high_phase = false;
+        let mut violated = false;
+        while t < secs {
+            let lum = if high_phase { high } else { low };
+            if detector.push(Duration::from_secs_f64(t), lum).is_some() {
+                violated = true;
+            }
+            high_phase = !high_phase;
+            t += half_period;
+        }
+        violated
+    }
+
+    #[test]
+    fn test_general_flash_violation() {
+        let mut detector = FlashDetector::new();
+        assert!(feed_square_wave(&mut detector, 5.0, 0.1, 0.6, 2.0));
+    }
+
+    #[test]
+    fn test_slow_flash_allowed() {
+        let mut detector = FlashDetector::new();
+        assert!(!feed_square_wave(&mut detector, 2.0, 0.1, 0.6, 3.0));
+        assert!(detector.flashes_in_window() <= MAX_FLASHES_PER_SECOND);
+    }
+
+    #[test]
+    fn test_flash_below_thresholds_ignored() {
+        // Swing smaller than 10%
+        let mut detector = FlashDetector::new();
+        assert!(!feed_square_wave(&mut detector, 10.0, 0.30, 0.35, 2.0));
+
+        // Darker frame too bright to count
+        let mut detector = FlashDetector::new();
+        assert!(!feed_square_wave(&mut detector, 10.0, 0.82, 0.98, 2.0));
+    }
+
+    #[test]
+    fn test_red_flash_violation() {
+        let red = red_flash_value(1.0, 0.0, 0.0);
+        let black = red_flash_value(0.0, 0.0, 0.0);
+        assert!(red > RED_FLASH_DELTA);
+        assert_eq!(red_flash_value(1.0, 1.0, 1.0), 0.0); // White is not a saturated red
+
+        let mut detector = FlashDetector::red();
+        assert!(feed_square_wave(&mut detector, 5.0, black, red, 2.0));
+    }
+
+    #[test]
+    fn test_small_area_flash_ignored() {
+        // 10x10 grid of 100x100 px cells, hazard area ~ 3 cells
+        let hazard = hazard_area_px(60.0, 96.0);
+        assert!(hazard > 20_000.0 && hazard < 40_000.0);
+        let field = visual_field_px(60.0, 96.0);
+        let mut area = AreaFlashDetector::new(FlashDetector::new(), 10, 10, (100.0, 100.0), field);
+
+        let mut violated_small = false;
+        let mut violated_large = false;
+        for i in 0..40 {
+            let t = Duration::from_millis(i * 50);
+            let lum = if i % 2 == 0 { 0.1 } else { 0.6 };
+            // One flashing cell (a thumbnail)
+            let mut samples = vec![0.2; 100];
+            samples[0] = lum;
+            violated_small |= area.push(t, &samples).is_some();
+        }
+
+        let mut area = AreaFlashDetector::new(FlashDetector::new(), 10, 10, (100.0, 100.0), field);
+        for i in 0..40 {
+            let t = Duration::from_millis(i * 50);
+            let lum = if i % 2 == 0 { 0.1 } else { 0.6 };
+            // Whole screen strobing
+            violated_large |= area.push(t, &vec![lum; 100]).is_some();
+        }
+
+        assert!(!violated_small);
+        assert!(violated_large);
+    }
+
+    // Strobes the given cells of a 10x10 grid of 100x100 px cells for two seconds
+    fn strobe_cells(cells: &[usize]) -> bool {
+        let mut area = AreaFlashDetector::new(FlashDetector::new(), 10, 10, (100.0, 100.0), visual_field_px(60.0, 96.0));
+        let mut violated = false;
+        for i in 0..40 {
+            let lum = if i % 2 == 0 { 0.1 } else { 0.6 };
+            let mut samples = vec![0.2; 100];
+            for &cell in cells {
+                samples[cell] = lum;
+            }
+            violated |= area.push(Duration::from_millis(i * 50), &samples).is_some();
+        }
+        violated
+    }
+
+    #[test]
+    fn test_flash_area_judged_per_visual_field() {
+        // Four corner cells: more than the hazard area in total, but no single
+        // visual field (~4 x 3 cells) sees more than one of them
+        assert!(!strobe_cells(&[0, 9, 90, 99]));
+        // The same area in one place fills a quarter of the field
+        assert!(strobe_cells(&[44, 45, 54, 55]));
+    }
+
+    #[test]
+    fn test_easing_curves() {
+        for easing in Easing::ALL {
+            assert!(easing.apply(0.0).abs() < 1e-9, "{:?} start", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-9, "{:?} end", easing);
+
+            // Monotonic: a transition never reverses direction
+            let mut prev = easing.interpolate(10.0, 90.0, 0.0);
+            for i in 1..=100 {
+                let v = easing.interpolate(10.0, 90.0, i as f64 / 100.0);
+                assert!(v >= prev - 1e-9, "{:?} reversed at step {}", easing, i);
+                prev = v;
+            }
+            assert!((prev - 90.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_timed_freeze_resumes_slowly() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(50.0, clock.clone());
+
+        guard.emergency_stop(Some(Duration::from_secs(60)));
+        assert_eq!(guard.mode, SafetyMode::EmergencyStop);
+        assert_eq!(guard.freeze_remaining(), Some(Duration::from_secs(60)));
+
+        clock.advance(Duration::from_secs(45));
+        assert!(guard.tick_transition().is_none());
+        assert_eq!(guard.freeze_remaining(), Some(Duration::from_secs(15)));
+
+        // Expiry is picked up by the next tick
+        clock.advance(Duration::from_secs(15));
+        guard.tick_transition();
+        assert_eq!(guard.mode, SafetyMode::Automatic);
+        assert!(guard.freeze_remaining().is_none());
+
+        guard.request_transition(90.0);
+        assert_eq!(guard.transition.as_ref().unwrap().duration.as_millis(), RESUME_RAMP_MS as u128);
+
+        // Only the first transition after resuming is stretched
+        guard.request_transition(20.0);
+        assert_eq!(guard.transition.as_ref().unwrap().duration.as_millis(), 750);
+    }
+
+    #[test]
+    fn test_indefinite_freeze_needs_resume() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(50.0, clock.clone());
+
+        guard.emergency_stop(None);
+        clock.advance(Duration::from_secs(24 * 3600));
+        guard.tick_transition();
+        assert_eq!(guard.mode, SafetyMode::EmergencyStop);
+        assert!(guard.freeze_remaining().is_none());
+
+        guard.resume();
+        assert_eq!(guard.mode, SafetyMode::Automatic);
+    }
+
+    #[test]
+    fn test_safe_mode_ramps_down_to_cap() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(80.0, clock.clone());
+        guard.set_mode_caps(40.0, 20.0);
+
+        guard.set_mode(SafetyMode::Safe);
+        assert_eq!(guard.mode, SafetyMode::Safe);
+        let trans = guard.transition.as_ref().expect("entering safe mode should dim");
+        assert_eq!(trans.target_brightness, 40.0);
+        assert_eq!(trans.duration.as_millis(), 2000);
+
+        // Requests above the cap are clamped and never faster than the mode's floor
+        clock.advance(Duration::from_secs(3));
+        guard.tick_transition();
+        guard.request_transition(30.0);
+        assert_eq!(guard.transition.as_ref().unwrap().duration.as_millis(), 2000);
+        guard.transition = None;
+        guard.current_brightness = 10.0;
+        guard.request_transition(90.0);
+        assert_eq!(guard.transition.as_ref().unwrap().target_brightness, 40.0);
+    }
+
+    #[test]
+    fn test_leaving_sleep_mode_is_slow() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(15.0, clock.clone());
+
+        guard.set_mode(SafetyMode::Sleep);
+        assert!(guard.transition.is_none());
+        assert!(guard.profile().flashbang_threshold < SafetyMode::Automatic.profile(40.0, 20.0).flashbang_threshold);
+
+        guard.set_mode(SafetyMode::Automatic);
+        guard.request_transition(70.0);
+        assert_eq!(guard.transition.as_ref().unwrap().duration.as_millis(), 5000);
+
+        // Emergency stop remembers the mode it interrupted
+        guard.set_mode(SafetyMode::Safe);
+        guard.emergency_stop(None);
+        guard.resume();
+        assert_eq!(guard.mode, SafetyMode::Safe);
+
+        // A mode switch while stopped does not lift the stop, it picks the mode to resume into
+        guard.emergency_stop(None);
+        guard.set_mode(SafetyMode::Sleep);
+        assert_eq!(guard.mode, SafetyMode::EmergencyStop);
+        guard.resume();
+        assert_eq!(guard.mode, SafetyMode::Sleep);
+        assert_eq!("sleep".parse::<SafetyMode>(), Ok(SafetyMode::Sleep));
+        assert_eq!("normal".parse::<SafetyMode>(), Ok(SafetyMode::Automatic));
+    }
+
+    #[test]
+    fn test_emergency_stop_dimmed() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(80.0, clock.clone());
+
+        guard.emergency_stop_dimmed(15.0, None);
+        assert_eq!(guard.mode, SafetyMode::EmergencyStop);
+
+        // Other requests are ignored, but the dim still plays out
+        guard.request_transition(90.0);
+        clock.advance(Duration::from_millis(100));
+        let mid = guard.tick_transition().unwrap();
+        assert!(mid < 80.0 && mid > 15.0);
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(guard.tick_transition(), Some(15.0));
+        assert!(guard.tick_transition().is_none());
+        assert_eq!(guard.current_brightness, 15.0);
+    }
+
+    #[test]
+    fn test_frozen_guard_ignores_force_instant_transition() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(80.0, clock.clone());
+        guard.emergency_stop(None);
+
+        // The safety cap is 0 while stopped; a fast dim must not ramp to black
+        guard.force_instant_transition(30.0);
+        assert!(guard.transition.is_none());
+        clock.advance(Duration::from_millis(300));
+        assert!(guard.tick_transition().is_none());
+        assert_eq!(guard.current_brightness, 80.0);
+
+        // Nor may it replace the dim emergency_stop_dimmed started
+        guard.emergency_stop_dimmed(40.0, None);
+        guard.force_instant_transition(10.0);
+        clock.advance(Duration::from_millis(300));
+        assert_eq!(guard.tick_transition(), Some(40.0));
+        assert_eq!(guard.current_brightness, 40.0);
+    }
 }
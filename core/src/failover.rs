@@ -0,0 +1,218 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::clock::{system_clock, SharedClock};
+use crate::hardware::{BackendCapabilities, BoxedController, BrightnessController, HardwareError};
+
+// Consecutive write failures before the next viable backend takes over
+pub const FAILOVER_THRESHOLD: u32 = 3;
+
+const BACKOFF_BASE: Duration = Duration::from_millis(250);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+// How often a better ranked backend that was failed over from is tried again
+pub const REPROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default)]
+pub struct BackendHealth {
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub backoff_until: Option<Instant>,
+    pub last_error: Option<String>,
+}
+
+impl BackendHealth {
+    // Doubles with every consecutive failure, up to BACKOFF_MAX
+    pub fn backoff(&self) -> Duration {
+        let doublings = self.consecutive_failures.saturating_sub(1).min(16);
+        (BACKOFF_BASE * 2u32.pow(doublings)).min(BACKOFF_MAX)
+    }
+
+    pub fn is_backing_off(&self, now: Instant) -> bool {
+        self.backoff_until.is_some_and(|until| now < until)
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.backoff_until = None;
+    }
+
+    pub fn record_failure(&mut self, now: Instant, error: &HardwareError) {
+        self.consecutive_failures += 1;
+        self.total_failures += 1;
+        self.backoff_until = Some(now + self.backoff());
+        self.last_error = Some(error.to_string());
+    }
+}
+
+// Which backend drives a display and why, as reported over IPC
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackendStatus {
+    pub display: String,
+    pub backend: String,
+    pub reason: String,
+    pub failures: u64,        // Write failures across all of the display's backends
+    pub standby: Vec<String>, // Other viable backends, best first
+}
+
+struct Backend {
+    controller: BoxedController,
+    health: BackendHealth,
+}
+
+// Every viable backend for one output in preference order. Writes go to the
+// active one; failing backends back off exponentially and, after
+// FAILOVER_THRESHOLD failures in a row, hand over to the next healthy one.
+// Better ranked backends are re-probed every REPROBE_INTERVAL.
+pub struct FailoverController {
+    backends: Vec<Backend>,
+    active: usize,
+    pending: Option<f64>, // Last value that did not land, written by flush
+    reason: String,
+    next_reprobe: Option<Instant>,
+    clock: SharedClock,
+}
+
+impl FailoverController {
+    pub fn new(preferred: BoxedController, fallbacks: Vec<BoxedController>, reason: String) -> Self {
+        Self::with_clock(preferred, fallbacks, reason, system_clock())
+    }
+
+    pub fn with_clock(preferred: BoxedController, fallbacks: Vec<BoxedController>, reason: String, clock: SharedClock) -> Self {
+        let backends = std::iter::once(preferred)
+            .chain(fallbacks)
+            .map(|controller| Backend { controller, health: BackendHealth::default() })
+            .collect();
+        Self { backends, active: 0, pending: None, reason, next_reprobe: None, clock }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn health(&self, index: usize) -> Option<&BackendHealth> {
+        self.backends.get(index).map(|b| &b.health)
+    }
+
+    // Best ranked healthy backend other than the active one
+    fn fail_over(&mut self, now: Instant) -> bool {
+        let next = (0..self.backends.len()).find(|&i| {
+            let h = &self.backends[i].health;
+            i != self.active && !h.is_backing_off(now) && h.consecutive_failures < FAILOVER_THRESHOLD
+        });
+        let Some(next) = next else { return false };
+
+        let failed = &self.backends[self.active];
+        self.reason = format!("{} failed {} times in a row ({})",
+                              failed.controller.name(),
+                              failed.health.consecutive_failures,
+                              failed.health.last_error.as_deref().unwrap_or("unknown error"));
+        warn!("Backend failover: {}, switching to {}", self.reason, self.backends[next].controller.name());
+        self.active = next;
+        self.next_reprobe = (next > 0).then(|| now + REPROBE_INTERVAL);
+        true
+    }
+
+    // Tries the better ranked backends with a real write; the first that works takes over again
+    fn reprobe(&mut self, value: f64, now: Instant) -> bool {
+        if self.next_reprobe.is_none_or(|at| now < at) {
+            return false;
+        }
+        self.next_reprobe = Some(now + REPROBE_INTERVAL);
+        for i in 0..self.active {
+            let backend = &mut self.backends[i];
+            if backend.health.is_backing_off(now) {
+                continue;
+            }
+            match backend.controller.set_brightness(value) {
+                Ok(()) => {
+                    backend.health.record_success();
+                    self.reason = format!("{} recovered", backend.controller.name());
+                    info!("Backend failover: {}, switching back", self.reason);
+                    self.active = i;
+                    self.next_reprobe = (i > 0).then(|| now + REPROBE_INTERVAL);
+                    return true;
+                }
+                Err(e) => backend.health.record_failure(now, &e),
+            }
+        }
+        false
+    }
+}
+
+impl BrightnessController for FailoverController {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        self.backends[self.active].controller.get_brightness()
+    }
+
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        let now = self.clock.now();
+        // A newer value replaces one that did not land
+        self.pending = None;
+        if self.reprobe(value, now) {
+            return Ok(());
+        }
+        loop {
+            let backend = &mut self.backends[self.active];
+            if backend.health.is_backing_off(now) {
+                if backend.health.consecutive_failures >= FAILOVER_THRESHOLD && self.fail_over(now) {
+                    continue;
+                }
+                // Held back rather than retried every tick; a newer write or the
+                // first flush after the backoff carries it
+                self.pending = Some(value);
+                return Ok(());
+            }
+            match backend.controller.set_brightness(value) {
+                Ok(()) => {
+                    backend.health.record_success();
+                    return Ok(());
+                }
+                Err(e) => {
+                    backend.health.record_failure(now, &e);
+                    if backend.health.consecutive_failures >= FAILOVER_THRESHOLD && self.fail_over(now) {
+                        continue;
+                    }
+                    self.pending = Some(value);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.backends[self.active].controller.name()
+    }
+
+    fn max_update_hz(&self) -> f64 {
+        self.backends[self.active].controller.max_update_hz()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.backends[self.active].controller.capabilities()
+    }
+
+    fn write_latency(&self) -> Option<Duration> {
+        self.backends[self.active].controller.write_latency()
+    }
+
+    fn flush(&mut self) -> Result<(), HardwareError> {
+        match self.pending {
+            Some(value) => self.set_brightness(value),
+            None => Ok(()),
+        }
+    }
+
+    fn status(&self) -> Option<BackendStatus> {
+        Some(BackendStatus {
+            display: String::new(),
+            backend: self.name().to_string(),
+            reason: self.reason.clone(),
+            failures: self.backends.iter().map(|b| b.health.total_failures).sum(),
+            standby: self.backends.iter().enumerate()
+                .filter(|(i, _)| *i != self.active)
+                .map(|(_, b)| b.controller.name().to_string())
+                .collect(),
+        })
+    }
+}
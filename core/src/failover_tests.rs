@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::ManualClock;
+    use crate::failover::{FailoverController, FAILOVER_THRESHOLD, REPROBE_INTERVAL};
+    use crate::hardware::{BrightnessController, HardwareError};
+    use chrono::Utc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // Fails every write while `broken` is set, records the ones that land
+    struct FlakyController {
+        name: &'static str,
+        broken: Arc<Mutex<bool>>,
+        writes: Arc<Mutex<Vec<f64>>>,
+    }
+
+    impl BrightnessController for FlakyController {
+        fn get_brightness(&self) -> Result<f64, HardwareError> {
+            Ok(self.writes.lock().unwrap().last().copied().unwrap_or(50.0))
+        }
+
+        fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+            if *self.broken.lock().unwrap() {
+                return Err(HardwareError::CommandFailed("device gone".into()));
+            }
+            self.writes.lock().unwrap().push(value);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    impl FlakyController {
+        fn new(name: &'static str) -> Self {
+            Self { name, broken: Arc::new(Mutex::new(false)), writes: Arc::new(Mutex::new(Vec::new())) }
+        }
+    }
+
+    #[test]
+    fn test_backoff_and_failover() {
+        let clock = ManualClock::shared(Utc::now());
+        let sysfs = FlakyController::new("sysfs");
+        let (sysfs_broken, sysfs_writes) = (sysfs.broken.clone(), sysfs.writes.clone());
+        let logind = FlakyController::new("logind");
+        let logind_writes = logind.writes.clone();
+        let mut controller = FailoverController::with_clock(Box::new(sysfs), vec![Box::new(logind)], "highest score".into(), clock.clone());
+
+        *sysfs_broken.lock().unwrap() = true;
+        assert!(controller.set_brightness(40.0).is_err());
+        // Backing off: writes are held back rather than retried every tick
+        for _ in 0..10 {
+            assert!(controller.set_brightness(41.0).is_ok());
+            clock.advance(Duration::from_millis(8));
+        }
+        assert_eq!(controller.health(0).unwrap().consecutive_failures, 1);
+
+        // Each retry waits twice as long; the threshold hands over to logind
+        for _ in 1..FAILOVER_THRESHOLD {
+            let backoff = controller.health(0).unwrap().backoff();
+            clock.advance(backoff);
+            controller.set_brightness(42.0).ok();
+        }
+        assert_eq!(controller.active(), 1);
+        assert_eq!(controller.name(), "logind");
+        assert_eq!(*logind_writes.lock().unwrap(), vec![42.0]);
+        let status = controller.status().unwrap();
+        assert!(status.reason.contains("sysfs failed 3 times"), "{}", status.reason);
+        assert_eq!(status.standby, vec!["sysfs".to_string()]);
+
+        // Re-probed once the interval has passed, and taken back once it works
+        *sysfs_broken.lock().unwrap() = false;
+        clock.advance(REPROBE_INTERVAL);
+        controller.set_brightness(43.0).unwrap();
+        assert_eq!(controller.active(), 0);
+        assert_eq!(*sysfs_writes.lock().unwrap(), vec![43.0]);
+        assert_eq!(controller.status().unwrap().failures, 3);
+    }
+
+    #[test]
+    fn test_value_held_back_lands_after_backoff() {
+        let clock = ManualClock::shared(Utc::now());
+        let sysfs = FlakyController::new("sysfs");
+        let (broken, writes) = (sysfs.broken.clone(), sysfs.writes.clone());
+        let mut controller = FailoverController::with_clock(Box::new(sysfs), Vec::new(), "only backend".into(), clock.clone());
+
+        *broken.lock().unwrap() = true;
+        assert!(controller.set_brightness(40.0).is_err());
+        *broken.lock().unwrap() = false;
+        // A transition's final value, written once while backing off
+        controller.set_brightness(30.0).unwrap();
+        controller.flush().unwrap();
+        assert!(writes.lock().unwrap().is_empty());
+
+        clock.advance(controller.health(0).unwrap().backoff());
+        controller.flush().unwrap();
+        assert_eq!(*writes.lock().unwrap(), vec![30.0]);
+        // Written once, not again on every flush
+        controller.flush().unwrap();
+        assert_eq!(*writes.lock().unwrap(), vec![30.0]);
+    }
+}
+
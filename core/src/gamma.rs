@@ -0,0 +1,520 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::unix::io::{AsFd, FromRawFd};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use wayland_client::protocol::wl_output::{self, WlOutput};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols_wlr::gamma_control::v1::client::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1;
+use wayland_protocols_wlr::gamma_control::v1::client::zwlr_gamma_control_v1::{self, ZwlrGammaControlV1};
+use x11rb::connection::Connection as _;
+use x11rb::protocol::randr::{Connection as OutputConnection, ConnectionExt as _, Crtc, GetScreenResourcesCurrentReply};
+use x11rb::rust_connection::RustConnection;
+use crate::failover::BackendStatus;
+use crate::hardware::{BackendCapabilities, BrightnessController, HardwareError, Privilege};
+use crate::sysfs::DrmConnector;
+
+// Never scale the LUT to black, the desktop must stay readable if the daemon dies mid-ramp
+pub const MIN_GAMMA_PERCENT: f64 = 2.0;
+
+// Panels apply roughly a 2.2 power curve after the LUT, so light scales with factor^2.2
+const DISPLAY_GAMMA: f64 = 2.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GammaMode {
+    Off,
+    // Only for outputs nothing else can dim
+    #[default]
+    Fallback,
+    // Also continue below the hardware backend's floor
+    Extend,
+}
+
+// Output names differ between the kernel and Wayland ("HDMI-A-1") and X11 drivers
+// ("HDMI-1", "HDMI1"); they are compared by this key
+pub fn output_key(name: &str) -> String {
+    let key: String = name.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase();
+    key.replacen("hdmia", "hdmi", 1).replacen("hdmib", "hdmi", 1)
+}
+
+// Built-in panels, which a backlight dims
+pub fn is_internal_output(name: &str) -> bool {
+    let key = output_key(name);
+    ["edp", "lvds", "dsi"].iter().any(|prefix| key.starts_with(prefix))
+}
+
+fn is_panel_id(id: &str) -> bool {
+    id.starts_with("backlight:") || id == "panel"
+}
+
+fn is_monitor_id(id: &str) -> bool {
+    id.starts_with("ddc:") || id.starts_with("ddcutil:")
+}
+
+// The only connected built-in panel, for backlights sysfs does not tie to a connector
+fn lone_panel(connectors: &[DrmConnector]) -> Option<String> {
+    let mut panels = connectors.iter().filter(|c| c.connected && is_internal_output(&c.name));
+    match (panels.next(), panels.next()) {
+        (Some(panel), None) => Some(panel.name.clone()),
+        _ => None,
+    }
+}
+
+// Output a display id drives, when sysfs tells
+pub fn connector_of(id: &str, connectors: &[DrmConnector]) -> Option<String> {
+    match id.split_once(':') {
+        Some(("gamma", output)) => Some(output.to_string()),
+        Some(("backlight", name)) => connectors.iter()
+            .find(|c| c.backlights.iter().any(|b| b == name))
+            .map(|c| c.name.clone())
+            .or_else(|| lone_panel(connectors)),
+        Some(("ddc", bus)) => connectors.iter().find(|c| c.ddc_bus.as_deref() == Some(bus)).map(|c| c.name.clone()),
+        None if is_panel_id(id) => lone_panel(connectors),
+        _ => None,
+    }
+}
+
+// Outputs none of the displays in `ids` reaches, for the gamma fallback. A
+// display whose connector is unknown covers every output of its kind (built-in
+// panel or external monitor) it could be; an output name sysfs does not know
+// is only left to gamma while nothing of its kind is managed at all.
+pub fn uncovered_outputs(outputs: &[String], connectors: &[DrmConnector], ids: &[String]) -> Vec<String> {
+    let mut covered = Vec::new();
+    let (mut panel_unmapped, mut monitor_unmapped) = (false, false);
+    for id in ids {
+        match connector_of(id, connectors) {
+            Some(output) => covered.push(output_key(&output)),
+            None if is_panel_id(id) => panel_unmapped = true,
+            None if is_monitor_id(id) => monitor_unmapped = true,
+            None => {}
+        }
+    }
+    let has_panel = ids.iter().any(|id| is_panel_id(id));
+    let has_monitor = ids.iter().any(|id| is_monitor_id(id));
+
+    outputs.iter().filter(|output| {
+        let key = output_key(output);
+        if covered.contains(&key) {
+            return false;
+        }
+        let known = connectors.iter().any(|c| output_key(&c.name) == key);
+        let internal = is_internal_output(output);
+        match (known, internal) {
+            (true, true) => !panel_unmapped,
+            (true, false) => !monitor_unmapped,
+            (false, true) => !has_panel,
+            (false, false) => !has_monitor,
+        }
+    }).cloned().collect()
+}
+
+// Active outputs of the display server, by connector name
+pub fn output_names() -> Result<Vec<String>, HardwareError> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let (_, _, state) = wl_outputs()?;
+        Ok(state.names.into_iter().flatten().collect())
+    } else if std::env::var_os("DISPLAY").is_some() {
+        let (conn, screen) = x11rb::connect(None).map_err(x11_error)?;
+        let root = conn.setup().roots[screen].root;
+        let resources = conn.randr_get_screen_resources_current(root).map_err(x11_error)?.reply().map_err(x11_error)?;
+        Ok(x11_outputs(&conn, &resources)?.into_iter().map(|(name, _)| name).collect())
+    } else {
+        Err(HardwareError::NotSupported)
+    }
+}
+
+pub fn identity_ramp(size: usize) -> Vec<u16> {
+    let last = size.saturating_sub(1).max(1) as f64;
+    (0..size).map(|i| (i as f64 / last * u16::MAX as f64).round() as u16).collect()
+}
+
+pub fn scale_ramp(base: &[u16], factor: f64) -> Vec<u16> {
+    let factor = factor.clamp(0.0, 1.0);
+    base.iter().map(|v| (*v as f64 * factor).round() as u16).collect()
+}
+
+// LUT factor that gives `percent` of full light output
+pub fn gamma_factor(percent: f64) -> f64 {
+    (percent.clamp(MIN_GAMMA_PERCENT, 100.0) / 100.0).powf(1.0 / DISPLAY_GAMMA)
+}
+
+struct X11Crtc {
+    crtc: Crtc,
+    red: Vec<u16>,
+    green: Vec<u16>,
+    blue: Vec<u16>,
+}
+
+// XRandR CRTC gamma. The ramps found at startup are scaled rather than replaced,
+// so a calibration profile loaded by the desktop survives.
+struct X11Gamma {
+    conn: RustConnection,
+    crtcs: Vec<X11Crtc>,
+}
+
+// Name and CRTC of every connected output that is lit
+fn x11_outputs(conn: &RustConnection, resources: &GetScreenResourcesCurrentReply) -> Result<Vec<(String, Crtc)>, HardwareError> {
+    let mut outputs = Vec::new();
+    for &output in &resources.outputs {
+        let info = conn.randr_get_output_info(output, resources.config_timestamp).map_err(x11_error)?.reply().map_err(x11_error)?;
+        if info.crtc != 0 && info.connection == OutputConnection::CONNECTED {
+            outputs.push((String::from_utf8_lossy(&info.name).to_string(), info.crtc));
+        }
+    }
+    Ok(outputs)
+}
+
+impl X11Gamma {
+    // Every CRTC, or only the one driving `output`
+    fn connect(output: Option<&str>) -> Result<Self, HardwareError> {
+        let (conn, screen) = x11rb::connect(None).map_err(x11_error)?;
+        let root = conn.setup().roots[screen].root;
+        let resources = conn.randr_get_screen_resources_current(root).map_err(x11_error)?.reply().map_err(x11_error)?;
+        let wanted: Option<Vec<Crtc>> = match output {
+            Some(name) => Some(x11_outputs(&conn, &resources)?.into_iter()
+                .filter(|(n, _)| output_key(n) == output_key(name))
+                .map(|(_, crtc)| crtc)
+                .collect()),
+            None => None,
+        };
+
+        let mut crtcs = Vec::new();
+        for crtc in resources.crtcs {
+            if wanted.as_ref().is_some_and(|w| !w.contains(&crtc)) {
+                continue;
+            }
+            let size = conn.randr_get_crtc_gamma_size(crtc).map_err(x11_error)?.reply().map_err(x11_error)?.size;
+            if size == 0 {
+                continue;
+            }
+            let gamma = conn.randr_get_crtc_gamma(crtc).map_err(x11_error)?.reply().map_err(x11_error)?;
+            crtcs.push(X11Crtc { crtc, red: gamma.red, green: gamma.green, blue: gamma.blue });
+        }
+        if crtcs.is_empty() {
+            return Err(HardwareError::NotSupported);
+        }
+        Ok(Self { conn, crtcs })
+    }
+
+    fn apply(&self, factor: f64) -> Result<(), HardwareError> {
+        for c in &self.crtcs {
+            let red = scale_ramp(&c.red, factor);
+            let green = scale_ramp(&c.green, factor);
+            let blue = scale_ramp(&c.blue, factor);
+            self.conn.randr_set_crtc_gamma(c.crtc, &red, &green, &blue).map_err(x11_error)?;
+        }
+        self.conn.flush().map_err(x11_error)
+    }
+}
+
+fn x11_error(e: impl std::fmt::Display) -> HardwareError {
+    HardwareError::CommandFailed(format!("X11 gamma: {}", e))
+}
+
+#[derive(Default)]
+struct WlGammaState {
+    manager: Option<ZwlrGammaControlManagerV1>,
+    outputs: Vec<WlOutput>,
+    names: Vec<Option<String>>, // Indexed like outputs, wl_output v4 and later
+    sizes: Vec<Option<u32>>, // Indexed like the controls, filled by gamma_size events
+    failed: Vec<bool>,
+}
+
+impl Dispatch<WlRegistry, ()> for WlGammaState {
+    fn event(state: &mut Self, registry: &WlRegistry, event: wl_registry::Event, _: &(), _: &Connection, qh: &QueueHandle<Self>) {
+        if let wl_registry::Event::Global { name, interface, version } = event {
+            match interface.as_str() {
+                "zwlr_gamma_control_manager_v1" => state.manager = Some(registry.bind(name, 1, qh, ())),
+                "wl_output" => {
+                    state.outputs.push(registry.bind(name, version.min(4), qh, state.names.len()));
+                    state.names.push(None);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<WlOutput, usize> for WlGammaState {
+    fn event(state: &mut Self, _: &WlOutput, event: wl_output::Event, index: &usize, _: &Connection, _: &QueueHandle<Self>) {
+        if let wl_output::Event::Name { name } = event {
+            state.names[*index] = Some(name);
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, usize> for WlGammaState {
+    fn event(state: &mut Self, _: &ZwlrGammaControlV1, event: zwlr_gamma_control_v1::Event, index: &usize, _: &Connection, _: &QueueHandle<Self>) {
+        match event {
+            zwlr_gamma_control_v1::Event::GammaSize { size } => state.sizes[*index] = Some(size),
+            zwlr_gamma_control_v1::Event::Failed => state.failed[*index] = true,
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(WlGammaState: ZwlrGammaControlManagerV1);
+
+// wlr-gamma-control (sway, Hyprland, river, ...). The compositor restores the
+// original ramps as soon as the control object or the connection goes away.
+struct WlGamma {
+    conn: Connection,
+    queue: EventQueue<WlGammaState>,
+    state: WlGammaState,
+    controls: Vec<ZwlrGammaControlV1>,
+}
+
+// Globals, then the outputs' own events (names)
+fn wl_outputs() -> Result<(Connection, EventQueue<WlGammaState>, WlGammaState), HardwareError> {
+    let conn = Connection::connect_to_env().map_err(wl_error)?;
+    let mut queue = conn.new_event_queue();
+    let qh = queue.handle();
+    conn.display().get_registry(&qh, ());
+    let mut state = WlGammaState::default();
+    queue.roundtrip(&mut state).map_err(wl_error)?;
+    queue.roundtrip(&mut state).map_err(wl_error)?;
+    Ok((conn, queue, state))
+}
+
+impl WlGamma {
+    // Every output, or only the one named `output`
+    fn connect(output: Option<&str>) -> Result<Self, HardwareError> {
+        let (conn, mut queue, mut state) = wl_outputs()?;
+        let qh = queue.handle();
+
+        let manager = state.manager.clone().ok_or(HardwareError::NotSupported)?;
+        let selected: Vec<WlOutput> = state.outputs.iter().zip(&state.names)
+            .filter(|(_, name)| output.is_none_or(|want| name.as_deref().is_some_and(|n| output_key(n) == output_key(want))))
+            .map(|(o, _)| o.clone())
+            .collect();
+        if selected.is_empty() {
+            return Err(HardwareError::NotSupported);
+        }
+        state.sizes = vec![None; selected.len()];
+        state.failed = vec![false; selected.len()];
+        let controls: Vec<ZwlrGammaControlV1> = selected.iter().enumerate()
+            .map(|(i, output)| manager.get_gamma_control(output, &qh, i))
+            .collect();
+        queue.roundtrip(&mut state).map_err(wl_error)?;
+
+        if state.sizes.iter().zip(&state.failed).all(|(size, failed)| size.is_none() || *failed) {
+            return Err(HardwareError::NotSupported);
+        }
+        Ok(Self { conn, queue, state, controls })
+    }
+
+    fn apply(&mut self, factor: f64) -> Result<(), HardwareError> {
+        for (i, control) in self.controls.iter().enumerate() {
+            let Some(size) = self.state.sizes[i] else { continue };
+            if self.state.failed[i] {
+                continue;
+            }
+            let ramp = scale_ramp(&identity_ramp(size as usize), factor);
+            let fd = ramp_file(&ramp)?;
+            control.set_gamma(fd.as_fd());
+        }
+        self.conn.flush().map_err(wl_error)?;
+        // Picks up `failed` (another client took the output) without blocking
+        self.queue.dispatch_pending(&mut self.state).map_err(wl_error)?;
+        if self.state.failed.iter().all(|f| *f) {
+            return Err(HardwareError::CommandFailed("wlr gamma control lost on every output".into()));
+        }
+        Ok(())
+    }
+}
+
+fn wl_error(e: impl std::fmt::Display) -> HardwareError {
+    HardwareError::CommandFailed(format!("Wayland gamma: {}", e))
+}
+
+// The protocol takes the red, green and blue ramps back to back in a file
+fn ramp_file(ramp: &[u16]) -> Result<File, HardwareError> {
+    // SAFETY: static NUL-terminated name, the returned fd is owned by the File below
+    let fd = unsafe { libc::memfd_create(c"epilyzer-gamma".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    let bytes: Vec<u8> = ramp.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    for _ in 0..3 {
+        file.write_all(&bytes)?;
+    }
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+enum GammaBackend {
+    X11(Box<X11Gamma>),
+    Wayland(Box<WlGamma>),
+}
+
+// Dims outputs by scaling their gamma LUT. Works on any panel, but only
+// darkens the pixels: the backlight keeps drawing the same power.
+pub struct GammaController {
+    backend: GammaBackend,
+    level: f64,
+}
+
+impl GammaController {
+    // Every output at once
+    pub fn new() -> Result<Self, HardwareError> {
+        Self::connect(None)
+    }
+
+    // Only the output named `output` ("HDMI-A-1")
+    pub fn for_output(output: &str) -> Result<Self, HardwareError> {
+        Self::connect(Some(output))
+    }
+
+    fn connect(output: Option<&str>) -> Result<Self, HardwareError> {
+        // XWayland accepts RandR gamma but the compositor ignores it
+        let backend = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            GammaBackend::Wayland(Box::new(WlGamma::connect(output)?))
+        } else if std::env::var_os("DISPLAY").is_some() {
+            GammaBackend::X11(Box::new(X11Gamma::connect(output)?))
+        } else {
+            return Err(HardwareError::NotSupported);
+        };
+        Ok(Self { backend, level: 100.0 })
+    }
+}
+
+impl BrightnessController for GammaController {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        Ok(self.level)
+    }
+
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        let factor = gamma_factor(value);
+        match &mut self.backend {
+            GammaBackend::X11(x) => x.apply(factor)?,
+            GammaBackend::Wayland(w) => w.apply(factor)?,
+        }
+        self.level = value.clamp(MIN_GAMMA_PERCENT, 100.0);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        match self.backend {
+            GammaBackend::X11(_) => "Gamma (XRandR)",
+            GammaBackend::Wayland(_) => "Gamma (wlr-gamma-control)",
+        }
+    }
+
+    fn max_update_hz(&self) -> f64 {
+        60.0
+    }
+
+    // Reports the level it last wrote; the LUT lands on the next frame
+    fn capabilities(&self) -> BackendCapabilities {
+        let ramp_size = match &self.backend {
+            GammaBackend::X11(x) => x.crtcs.first().map_or(0, |c| c.red.len()),
+            GammaBackend::Wayland(w) => w.state.sizes.iter().flatten().next().copied().unwrap_or(0) as usize,
+        };
+        BackendCapabilities {
+            readback: false,
+            resolution: ramp_size as u32,
+            latency: Duration::from_millis(16),
+            shows_osd: false,
+            privilege: Privilege::DisplayServer,
+        }
+    }
+}
+
+impl Drop for GammaController {
+    fn drop(&mut self) {
+        if let GammaBackend::X11(x) = &self.backend {
+            if let Err(e) = x.apply(1.0) {
+                warn!("Failed to restore gamma ramps: {}", e);
+            }
+        }
+    }
+}
+
+// A hardware backend for the normal range that hands over to a gamma backend
+// below `hardware_floor`. Values stay linear light output, so 5% on a floor of
+// 10 is the hardware at 10% with the LUT halving what is left.
+pub struct ExtendedRangeController<H, G> {
+    hardware: H,
+    gamma: G,
+    hardware_floor: f64,
+    gamma_level: f64,
+    name: String,
+}
+
+impl<H: BrightnessController, G: BrightnessController> ExtendedRangeController<H, G> {
+    pub fn new(hardware: H, gamma: G, hardware_floor: f64) -> Self {
+        let name = format!("{} + {}", hardware.name(), gamma.name());
+        let gamma_level = gamma.get_brightness().unwrap_or(100.0);
+        info!("Extending '{}' below {:.0}% with '{}'", hardware.name(), hardware_floor, gamma.name());
+        Self { hardware, gamma, hardware_floor: hardware_floor.clamp(1.0, 100.0), gamma_level, name }
+    }
+
+    pub fn hardware(&self) -> &H {
+        &self.hardware
+    }
+
+    pub fn gamma(&self) -> &G {
+        &self.gamma
+    }
+}
+
+impl<H: BrightnessController, G: BrightnessController> BrightnessController for ExtendedRangeController<H, G> {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        Ok(self.hardware.get_brightness()? * self.gamma_level / 100.0)
+    }
+
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        let value = value.clamp(0.0, 100.0);
+        if value >= self.hardware_floor {
+            // Lift the LUT before the backlight moves so the two never compound downwards
+            if self.gamma_level < 100.0 {
+                self.gamma.set_brightness(100.0)?;
+                self.gamma_level = 100.0;
+            }
+            self.hardware.set_brightness(value)
+        } else {
+            self.hardware.set_brightness(self.hardware_floor)?;
+            let level = value / self.hardware_floor * 100.0;
+            self.gamma.set_brightness(level)?;
+            self.gamma_level = level;
+            Ok(())
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn max_update_hz(&self) -> f64 {
+        self.hardware.max_update_hz().min(self.gamma.max_update_hz())
+    }
+
+    fn status(&self) -> Option<BackendStatus> {
+        self.hardware.status()
+    }
+
+    fn write_latency(&self) -> Option<Duration> {
+        self.hardware.write_latency()
+    }
+
+    fn flush(&mut self) -> Result<(), HardwareError> {
+        self.hardware.flush()
+    }
+
+    // The hardware's native levels: above the floor, where transitions spend
+    // most of their range, those are the steps that reach the screen
+    fn capabilities(&self) -> BackendCapabilities {
+        let hardware = self.hardware.capabilities();
+        let gamma = self.gamma.capabilities();
+        BackendCapabilities {
+            readback: hardware.readback && gamma.readback,
+            resolution: hardware.resolution,
+            latency: hardware.latency.max(gamma.latency),
+            ..hardware
+        }
+    }
+}
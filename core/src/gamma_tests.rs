@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use crate::gamma::{connector_of, gamma_factor, identity_ramp, output_key, scale_ramp, uncovered_outputs, ExtendedRangeController, MIN_GAMMA_PERCENT};
+    use crate::hardware::{BrightnessController, DummyController};
+    use crate::sysfs::DrmConnector;
+
+    #[test]
+    fn test_gamma_ramps() {
+        let ramp = identity_ramp(256);
+        assert_eq!(ramp[0], 0);
+        assert_eq!(ramp[255], u16::MAX);
+        assert!(ramp.windows(2).all(|w| w[0] < w[1]));
+
+        let half = scale_ramp(&ramp, 0.5);
+        assert_eq!(half[255], 32768);
+        assert_eq!(scale_ramp(&ramp, 1.5), ramp);
+
+        // Light output follows the panel's 2.2 curve and never reaches black
+        assert!((gamma_factor(100.0) - 1.0).abs() < 1e-9);
+        assert!((gamma_factor(50.0).powf(2.2) - 0.5).abs() < 1e-9);
+        assert_eq!(gamma_factor(0.0), gamma_factor(MIN_GAMMA_PERCENT));
+    }
+
+    #[test]
+    fn test_extended_range_split() {
+        let mut ext = ExtendedRangeController::new(DummyController::new(), DummyController::new(), 10.0);
+
+        ext.set_brightness(40.0).unwrap();
+        assert_eq!(ext.hardware().get_brightness().unwrap(), 40.0);
+        assert_eq!(ext.get_brightness().unwrap(), 40.0);
+
+        // Below the floor the backlight parks and the LUT takes the rest
+        ext.set_brightness(5.0).unwrap();
+        assert_eq!(ext.hardware().get_brightness().unwrap(), 10.0);
+        assert_eq!(ext.gamma().get_brightness().unwrap(), 50.0);
+        assert!((ext.get_brightness().unwrap() - 5.0).abs() < 1e-9);
+
+        ext.set_brightness(20.0).unwrap();
+        assert_eq!(ext.gamma().get_brightness().unwrap(), 100.0);
+        assert_eq!(ext.get_brightness().unwrap(), 20.0);
+    }
+
+    fn connector(name: &str, ddc_bus: Option<&str>, backlights: &[&str]) -> DrmConnector {
+        DrmConnector {
+            name: name.to_string(),
+            connected: true,
+            ddc_bus: ddc_bus.map(str::to_string),
+            backlights: backlights.iter().map(|b| b.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_outputs_matched_to_displays() {
+        assert_eq!(output_key("HDMI-A-1"), output_key("HDMI-1"));
+        assert_eq!(output_key("HDMI1"), output_key("HDMI-1"));
+        assert_ne!(output_key("DP-1"), output_key("DP-2"));
+
+        let connectors = vec![
+            connector("DP-1", Some("i2c-5"), &[]),
+            connector("HDMI-A-1", None, &[]),
+            connector("eDP-1", None, &["intel_backlight"]),
+        ];
+        assert_eq!(connector_of("backlight:intel_backlight", &connectors).as_deref(), Some("eDP-1"));
+        assert_eq!(connector_of("panel", &connectors).as_deref(), Some("eDP-1"));
+        assert_eq!(connector_of("ddc:i2c-5", &connectors).as_deref(), Some("DP-1"));
+        assert_eq!(connector_of("ddc:i2c-9", &connectors), None);
+
+        // X11 names the outputs differently; only the monitor without DDC/CI is left to gamma
+        let outputs: Vec<String> = ["eDP1", "DP1", "HDMI1"].iter().map(|o| o.to_string()).collect();
+        let ids = vec!["backlight:intel_backlight".to_string(), "ddc:i2c-5".to_string()];
+        assert_eq!(uncovered_outputs(&outputs, &connectors, &ids), vec!["HDMI1".to_string()]);
+
+        // A monitor whose bus sysfs does not tie to a connector could be any of them
+        let ids = vec!["backlight:intel_backlight".to_string(), "ddc:i2c-9".to_string()];
+        assert!(uncovered_outputs(&outputs, &connectors, &ids).is_empty());
+
+        // Nothing managed: every output gets its own LUT
+        assert_eq!(uncovered_outputs(&outputs, &connectors, &[]), outputs);
+    }
+}
@@ -1,8 +1,21 @@
+use std::collections::VecDeque;
 use std::process::Command;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{info, warn};
+use crate::clock::{system_clock, SharedClock};
+use crate::config::BrightnessConfig;
+use crate::ddc::{self, DdcController};
+use crate::failover::{BackendStatus, FailoverController};
+use crate::gamma::{self, ExtendedRangeController, GammaController, GammaMode};
+use crate::sysfs::SysfsRoot;
+use crate::transition::{self, TransitionPlan};
+use crate::epilepsy::{BrightnessSpace, Easing, EpilepsyGuard, SafetyMode, FLASH_LUMINANCE_DELTA, MAX_CHANGE_FREQUENCY_HZ, RESUME_RAMP_MS};
 
 #[derive(Error, Debug)]
 pub enum HardwareError {
@@ -14,15 +27,831 @@ pub enum HardwareError {
     NotSupported,
     #[error("Value out of range")]
     OutOfRange,
+    #[error("DDC/CI protocol error: {0}")]
+    Protocol(String),
 }
 
 pub trait BrightnessController {
     fn get_brightness(&self) -> Result<f64, HardwareError>;
     fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError>;
     fn name(&self) -> &str;
+
+    // Highest write rate the device handles without lag, flicker or OSD spam
+    fn max_update_hz(&self) -> f64 {
+        125.0
+    }
+
+    // Active backend and failover state, for wrappers that manage several
+    fn status(&self) -> Option<BackendStatus> {
+        None
+    }
+
+    // Time the device took for the last write, for backends whose set_brightness
+    // only queues it; None when set_brightness returns once the write landed
+    fn write_latency(&self) -> Option<Duration> {
+        None
+    }
+
+    // Writes a value set_brightness had to hold back (backend backing off) once
+    // it can; called on ticks that bring no new value for the display
+    fn flush(&mut self) -> Result<(), HardwareError> {
+        Ok(())
+    }
+
+    // Conservative guess for backends that do not describe themselves
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: false,
+            resolution: 101,
+            latency: Duration::from_secs_f64(1.0 / self.max_update_hz()),
+            shows_osd: false,
+            privilege: Privilege::None,
+        }
+    }
+}
+
+// Access a backend needs beyond a plain user process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Privilege {
+    None,
+    VideoGroup,    // Writable sysfs backlight (udev rule / video group)
+    I2cGroup,      // Read-write /dev/i2c-*
+    SessionBus,    // Active session with the service running
+    DisplayServer, // X11 or wlroots client connection
+}
+
+// What discovery weighs when several backends reach the same output
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendCapabilities {
+    pub readback: bool,    // get_brightness reports the device, not the last write
+    pub resolution: u32,   // Distinct hardware levels
+    pub latency: Duration, // Typical time for one write to take effect
+    pub shows_osd: bool,   // Every write pops the desktop's brightness OSD
+    pub privilege: Privilege,
+}
+
+impl BackendCapabilities {
+    pub fn score(&self) -> i32 {
+        let mut score = 0;
+        if self.readback {
+            score += 20;
+        }
+        // Up to 30 each: ~20 for 101 levels, 30 from 1024; nothing left at 300 ms
+        score += ((self.resolution.max(2) as f64).log2() * 3.0).min(30.0) as i32;
+        score += (30.0 - self.latency.as_secs_f64() * 100.0).max(0.0) as i32;
+        if self.shows_osd {
+            score -= 25;
+        }
+        score - match self.privilege {
+            Privilege::None => 0,
+            Privilege::VideoGroup | Privilege::I2cGroup => 2,
+            Privilege::SessionBus | Privilege::DisplayServer => 5,
+        }
+    }
+}
+
+impl std::fmt::Display for BackendCapabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} levels, {}ms, {}, {}, {:?}",
+               self.resolution,
+               self.latency.as_millis(),
+               if self.readback { "readback" } else { "no readback" },
+               if self.shows_osd { "OSD" } else { "silent" },
+               self.privilege)
+    }
+}
+
+impl<T: BrightnessController + ?Sized> BrightnessController for Box<T> {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        (**self).get_brightness()
+    }
+
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        (**self).set_brightness(value)
+    }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn max_update_hz(&self) -> f64 {
+        (**self).max_update_hz()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        (**self).capabilities()
+    }
+
+    fn status(&self) -> Option<BackendStatus> {
+        (**self).status()
+    }
+
+    fn write_latency(&self) -> Option<Duration> {
+        (**self).write_latency()
+    }
+
+    fn flush(&mut self) -> Result<(), HardwareError> {
+        (**self).flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SafetyAction {
+    Vetoed,    // Write dropped, hardware keeps its previous value
+    Flattened, // Write clamped so it no longer reverses direction
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyEvent {
+    pub timestamp: DateTime<Utc>,
+    pub action: SafetyAction,
+    pub requested: f64,
+    pub applied: Option<f64>,
+    pub reversals: usize,
+}
+
+pub type SafetyEventLog = Arc<Mutex<VecDeque<SafetyEvent>>>;
+
+const SAFETY_EVENT_LOG_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+}
+
+// Last line of defence: sees every value that reaches the real backend, whoever
+// asked for it, and refuses to let the writes oscillate faster than
+// MAX_CHANGE_FREQUENCY_HZ reversals per second.
+pub struct SafetyAuditor<C: BrightnessController> {
+    inner: C,
+    min_delta: f64,
+    window: Duration,
+    last_written: Option<f64>,
+    extreme: Option<f64>,
+    direction: Option<Direction>,
+    reversals: VecDeque<Instant>,
+    events: SafetyEventLog,
+    clock: SharedClock,
+}
+
+impl<C: BrightnessController> SafetyAuditor<C> {
+    pub fn new(inner: C, min_delta: f64) -> Self {
+        Self::with_clock(inner, min_delta, system_clock())
+    }
+
+    pub fn with_clock(inner: C, min_delta: f64, clock: SharedClock) -> Self {
+        Self {
+            inner,
+            min_delta,
+            window: Duration::from_secs(1),
+            last_written: None,
+            extreme: None,
+            direction: None,
+            reversals: VecDeque::new(),
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            clock,
+        }
+    }
+
+    pub fn events(&self) -> SafetyEventLog {
+        self.events.clone()
+    }
+
+    // Lets several auditors (one per display) report into one log
+    pub fn set_event_log(&mut self, events: SafetyEventLog) {
+        self.events = events;
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn record(&self, event: SafetyEvent) {
+        warn!("Safety auditor {:?} write of {:.1}% ({} reversals in window)", event.action, event.requested, event.reversals);
+        if let Ok(mut log) = self.events.lock() {
+            if log.len() >= SAFETY_EVENT_LOG_CAPACITY {
+                log.pop_front();
+            }
+            log.push_back(event);
+        }
+    }
+
+    fn write(&mut self, value: f64, now: Instant, reversed: Option<Direction>) -> Result<(), HardwareError> {
+        self.inner.set_brightness(value)?;
+        self.last_written = Some(value);
+        if let Some(direction) = reversed {
+            if self.direction.is_some() {
+                self.reversals.push_back(now);
+            }
+            self.direction = Some(direction);
+            self.extreme = Some(value);
+        } else {
+            // Same direction (or below the delta): extend the current extreme
+            self.extreme = match (self.extreme, self.direction) {
+                (Some(e), Some(Direction::Up)) => Some(e.max(value)),
+                (Some(e), Some(Direction::Down)) => Some(e.min(value)),
+                (Some(e), None) => Some(e),
+                (None, _) => Some(value),
+            };
+        }
+        Ok(())
+    }
+}
+
+impl<C: BrightnessController> BrightnessController for SafetyAuditor<C> {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        self.inner.get_brightness()
+    }
+
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        let now = self.clock.now();
+        while let Some(&oldest) = self.reversals.front() {
+            if now.duration_since(oldest) > self.window {
+                self.reversals.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let extreme = match self.extreme {
+            Some(e) => e,
+            None => return self.write(value, now, None),
+        };
+
+        let delta = value - extreme;
+        let direction = if delta > 0.0 { Direction::Up } else { Direction::Down };
+        if delta.abs() < self.min_delta || self.direction == Some(direction) {
+            return self.write(value, now, None);
+        }
+
+        // First significant move only sets the direction, it is not a reversal
+        let max_reversals = (MAX_CHANGE_FREQUENCY_HZ * self.window.as_secs_f64()) as usize;
+        if self.direction.is_none() || self.reversals.len() < max_reversals {
+            return self.write(value, now, Some(direction));
+        }
+
+        // Too many reversals: keep the write inside the dead-band around the extreme
+        let limit = self.min_delta * 0.99;
+        let flattened = match direction {
+            Direction::Up => value.min(extreme + limit),
+            Direction::Down => value.max(extreme - limit),
+        };
+        let reversals = self.reversals.len() + 1;
+
+        let unchanged = self.last_written.is_some_and(|last| (last - flattened).abs() < 0.01);
+        if unchanged {
+            self.record(SafetyEvent {
+                timestamp: self.clock.utc_now(),
+                action: SafetyAction::Vetoed,
+                requested: value,
+                applied: None,
+                reversals,
+            });
+            return Ok(());
+        }
+
+        self.write(flattened, now, None)?;
+        self.record(SafetyEvent {
+            timestamp: self.clock.utc_now(),
+            action: SafetyAction::Flattened,
+            requested: value,
+            applied: Some(flattened),
+            reversals,
+        });
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn max_update_hz(&self) -> f64 {
+        self.inner.max_update_hz()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn status(&self) -> Option<BackendStatus> {
+        self.inner.status()
+    }
+
+    fn write_latency(&self) -> Option<Duration> {
+        self.inner.write_latency()
+    }
+
+    fn flush(&mut self) -> Result<(), HardwareError> {
+        self.inner.flush()
+    }
+}
+
+pub type BoxedController = Box<dyn BrightnessController + Send>;
+
+// One way of reaching an output, as found by discovery
+pub struct ProbeCandidate {
+    pub backend: &'static str,
+    pub result: Result<BoxedController, HardwareError>,
+}
+
+impl ProbeCandidate {
+    pub fn new<C: BrightnessController + Send + 'static>(backend: &'static str, result: Result<C, HardwareError>) -> Self {
+        Self { backend, result: result.map(|c| Box::new(c) as BoxedController) }
+    }
+}
+
+// Highest scoring working candidate for one output, logging why every other
+// one lost. Ties go to the earlier candidate. The rest stay behind it as
+// failover backends in score order. The display keeps the output's id
+// whichever backend wins.
+pub fn select_backend(output: &str, candidates: Vec<ProbeCandidate>) -> Option<(String, BoxedController)> {
+    let mut viable: Vec<(i32, &'static str, BoxedController)> = Vec::new();
+    for candidate in candidates {
+        let controller = match candidate.result {
+            Ok(c) => c,
+            Err(e) => {
+                info!("{}: {} rejected: {}", output, candidate.backend, e);
+                continue;
+            }
+        };
+        let caps = controller.capabilities();
+        let score = caps.score();
+        info!("{}: {} scored {} ({})", output, candidate.backend, score, caps);
+        viable.push((score, candidate.backend, controller));
+    }
+    // Stable, so equal scores keep probe order
+    viable.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+
+    let mut ranked = viable.into_iter();
+    let Some((score, backend, preferred)) = ranked.next() else {
+        warn!("{}: no usable backend", output);
+        return None;
+    };
+    info!("{}: selected {} via {} (score {})", output, preferred.name(), backend, score);
+    let reason = format!("{} scored highest ({})", backend, score);
+    let fallbacks = ranked.map(|(_, _, c)| c).collect();
+    Some((output.to_string(), Box::new(FailoverController::new(preferred, fallbacks, reason))))
+}
+
+pub fn is_kde_session() -> bool {
+    std::env::var("KDE_FULL_SESSION").map(|v| v == "true").unwrap_or(false)
+        || std::env::var("DESKTOP_SESSION").map(|v| v.contains("plasma")).unwrap_or(false)
+}
+
+pub fn is_gnome_session() -> bool {
+    gnome_session(std::env::var("XDG_CURRENT_DESKTOP").ok().as_deref(), std::env::var("DESKTOP_SESSION").ok().as_deref())
+}
+
+// XDG_CURRENT_DESKTOP is a colon separated list, e.g. "ubuntu:GNOME"
+pub(crate) fn gnome_session(current_desktop: Option<&str>, desktop_session: Option<&str>) -> bool {
+    current_desktop.is_some_and(|v| v.split(':').any(|d| d == "GNOME"))
+        || desktop_session.is_some_and(|v| v.contains("gnome"))
 }
 
+// Largest single write the scheduler allows on slow backends: below the 10%
+// change WCAG counts as half of a flash
+pub const MAX_SCHEDULED_STEP: f64 = FLASH_LUMINANCE_DELTA * 100.0;
 
+// Weight of the newest sample in the write latency average
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+// Decides when a display may be written. The guard still samples the eased
+// curve every tick; only the samples the device can accept are sent.
+#[derive(Debug, Clone)]
+pub struct UpdateScheduler {
+    max_hz: f64,
+    latency: Option<Duration>, // Moving average of set_brightness duration
+    last_write: Option<Instant>,
+}
+
+impl UpdateScheduler {
+    pub fn new(max_hz: f64) -> Self {
+        Self { max_hz: max_hz.max(0.1), latency: None, last_write: None }
+    }
+
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    // Declared rate, slowed further if writes take longer than that
+    pub fn interval(&self) -> Duration {
+        let declared = Duration::from_secs_f64(1.0 / self.max_hz);
+        self.latency.map_or(declared, |l| declared.max(l))
+    }
+
+    // Follows a failover to a backend with a different rate
+    pub fn set_max_hz(&mut self, max_hz: f64) {
+        self.max_hz = max_hz.max(0.1);
+    }
+
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.last_write.is_none_or(|last| now.duration_since(last) >= self.interval())
+    }
+
+    pub fn record_write(&mut self, started: Instant, finished: Instant) {
+        self.record_latency(started, finished.duration_since(started));
+    }
+
+    pub fn record_latency(&mut self, started: Instant, latency: Duration) {
+        let sample = latency.as_secs_f64();
+        let avg = match self.latency {
+            Some(l) => l.as_secs_f64() * (1.0 - LATENCY_SMOOTHING) + sample * LATENCY_SMOOTHING,
+            None => sample,
+        };
+        self.latency = Some(Duration::from_secs_f64(avg));
+        self.last_write = Some(started);
+    }
+
+    // Shortest transition over `distance` that keeps every write within
+    // MAX_SCHEDULED_STEP at the curve's steepest point. One spare interval
+    // absorbs writes landing a tick late.
+    pub fn min_duration(&self, distance: f64, easing: Easing) -> Duration {
+        let steps = (distance.abs() * easing.max_slope() / MAX_SCHEDULED_STEP).ceil() as u32;
+        self.interval() * (steps + 1)
+    }
+}
+
+// Written to a hot-plugged display whose current level cannot be read back
+pub const HOTPLUG_START_BRIGHTNESS: f64 = 20.0;
+
+// One physical output: its own guard (so per-display transitions are still
+// rate limited), plus a linear offset/scale applied to the shared target.
+pub struct ManagedDisplay {
+    pub id: String,
+    pub controller: BoxedController,
+    pub guard: EpilepsyGuard,
+    pub offset: f64,
+    pub scale: f64,
+    pub scheduler: UpdateScheduler,
+    followed: Option<Instant>, // Start time of the master transition being followed
+    plan: Option<TransitionPlan>, // Native steps of the current transition on a coarse device
+    stepping: Option<Stepping>,   // A move the planner refused, made one level at a time
+}
+
+// A move whose single native levels exceed MAX_DELTA_PER_STEP. Each level is a
+// separate write, `spacing` after the previous one.
+struct Stepping {
+    target: f64,
+    levels: u32,
+    spacing: Duration,
+    last: Option<Instant>,
+}
+
+impl ManagedDisplay {
+    pub fn new(id: String, controller: BoxedController, guard: EpilepsyGuard) -> Self {
+        let scheduler = UpdateScheduler::new(controller.max_update_hz());
+        Self { id, controller, guard, offset: 0.0, scale: 1.0, scheduler, followed: None, plan: None, stepping: None }
+    }
+
+    pub fn map(&self, value: f64) -> f64 {
+        value * self.scale + self.offset
+    }
+
+    pub fn plan(&self) -> Option<&TransitionPlan> {
+        self.plan.as_ref()
+    }
+
+    // Guard transition plus, when the device has too few levels for the eased
+    // curve, the plan its writes follow. The guard ramp is stretched to match.
+    // A move the planner refuses is logged and made one native level at a time.
+    fn start_transition(&mut self, target: f64, duration: Duration, easing: Easing) {
+        self.guard.request_transition_with(target, duration, easing);
+        self.plan = None;
+        let was_stepping = self.stepping.take().is_some();
+        let levels = self.controller.capabilities().resolution;
+        let (space, interval) = (self.guard.space, self.scheduler.interval());
+        let Some(t) = self.guard.transition.as_mut() else { return };
+        match TransitionPlan::new(t.initial_brightness, t.target_brightness, t.duration, levels, space, interval) {
+            Ok(plan) => {
+                if let Some(plan) = &plan {
+                    t.duration = t.duration.max(plan.duration());
+                }
+                self.plan = plan;
+            }
+            Err(e) => {
+                if !was_stepping {
+                    warn!("'{}': {}, moving one level at a time", self.id, e);
+                }
+                self.stepping = Some(Stepping { target: t.target_brightness, levels, spacing: e.spacing, last: None });
+                self.guard.transition = None;
+            }
+        }
+    }
+
+    // The next level of a refused move once its slot has come
+    fn next_step(&mut self) -> Option<f64> {
+        let stepping = self.stepping.as_mut()?;
+        let now = self.guard.clock().now();
+        if stepping.last.is_some_and(|last| now.duration_since(last) < stepping.spacing) {
+            return None;
+        }
+        stepping.last = Some(now);
+        let next = transition::next_level(self.guard.current_brightness, stepping.target, stepping.levels);
+        if (next - stepping.target).abs() < 0.1 {
+            self.stepping = None;
+        }
+        self.guard.request_transition_with(next, Duration::ZERO, Easing::Linear);
+        self.guard.tick_transition()
+    }
+}
+
+#[derive(Default)]
+pub struct DisplayRegistry {
+    displays: Vec<ManagedDisplay>,
+}
+
+impl DisplayRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Every output reachable with the given method ("backlight", "ddcutil" or
+    // "auto" for all of them), keyed by a stable id such as "backlight:intel_backlight".
+    // Each output gets the best scoring backend that reaches it.
+    pub fn discover(config: &BrightnessConfig, sysfs: &SysfsRoot) -> Vec<(String, BoxedController)> {
+        Self::discover_except(config, sysfs, &[])
+    }
+
+    // Like discover, but leaves the displays in `held` alone: their backends are
+    // not probed again (a second gamma control on a held output fails, and
+    // dropping the held one would restore the full LUT in one frame)
+    pub fn discover_except(config: &BrightnessConfig, sysfs: &SysfsRoot, held: &[String]) -> Vec<(String, BoxedController)> {
+        let is_held = |id: &str| held.iter().any(|h| h == id);
+        let method = config.method.as_str();
+        let mut found: Vec<(String, BoxedController)> = Vec::new();
+
+        if method != "ddcutil" {
+            let names = sysfs.backlights();
+            // The desktop services drive the primary panel, so they compete for
+            // the first backlight, or stand alone on panels without one
+            let mut desktop = Vec::new();
+            if is_kde_session() {
+                desktop.push(ProbeCandidate::new("KDE PowerManagement", KdeBrightnessController::new()));
+            }
+            if is_gnome_session() {
+                desktop.push(ProbeCandidate::new("gsd-power", GnomeBrightnessController::new()));
+            }
+            for name in names {
+                let id = format!("backlight:{}", name);
+                if is_held(&id) {
+                    desktop.clear();
+                    continue;
+                }
+                let mut candidates = vec![
+                    ProbeCandidate::new("sysfs", BacklightController::with_root(sysfs, &name)),
+                    ProbeCandidate::new("logind", LogindBacklightController::with_root(sysfs, &name)),
+                ];
+                candidates.append(&mut desktop);
+                found.extend(select_backend(&id, candidates));
+            }
+            if !desktop.is_empty() && !is_held("panel") {
+                found.extend(select_backend("panel", desktop));
+            }
+        }
+
+        if method != "backlight" {
+            let mut ddcutil = DdcUtilController::detect();
+            for display in ddc::discover(sysfs) {
+                let bus = display.bus.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                let id = format!("ddc:{}", bus);
+                let via_ddcutil = ddcutil.iter().position(|(_, b)| b.as_deref() == Some(bus.as_str())).map(|i| ddcutil.remove(i).0);
+                if is_held(&id) {
+                    continue;
+                }
+                let mut candidates = vec![ProbeCandidate::new("i2c-dev", DdcController::new(&display))];
+                if let Some(number) = via_ddcutil {
+                    candidates.push(ProbeCandidate::new("ddcutil", Ok(DdcUtilController::new(number))));
+                }
+                found.extend(select_backend(&id, candidates));
+            }
+            // Monitors only ddcutil reaches (no /dev/i2c access of our own)
+            for (number, _) in ddcutil {
+                let id = format!("ddcutil:{}", number);
+                if is_held(&id) {
+                    continue;
+                }
+                found.extend(select_backend(&id, vec![ProbeCandidate::new("ddcutil", Ok(DdcUtilController::new(number)))]));
+            }
+        }
+
+        if config.gamma != GammaMode::Off {
+            match gamma::output_names() {
+                Ok(outputs) => Self::attach_gamma(config, sysfs, &outputs, held, &mut found),
+                Err(e) => info!("Gamma dimming unavailable: {}", e),
+            }
+        }
+
+        found
+    }
+
+    // Gives every output no hardware backend reaches its own gamma LUT and, in
+    // "extend", pairs each newly found hardware display with the LUT of its output.
+    // Held displays still cover their outputs.
+    fn attach_gamma(config: &BrightnessConfig, sysfs: &SysfsRoot, outputs: &[String], held: &[String], found: &mut Vec<(String, BoxedController)>) {
+        let connectors = sysfs.drm_connectors();
+        let ids: Vec<String> = found.iter().map(|(id, _)| id.clone()).chain(held.iter().cloned()).collect();
+
+        if config.gamma == GammaMode::Extend {
+            let lone = ids.len() == 1 && outputs.len() == 1;
+            for (id, hardware) in std::mem::take(found) {
+                let output = gamma::connector_of(&id, &connectors)
+                    .and_then(|c| outputs.iter().find(|o| gamma::output_key(o) == gamma::output_key(&c)))
+                    .or(if lone { outputs.first() } else { None });
+                let Some(output) = output else {
+                    warn!("No output found for '{}', gamma cannot extend it", id);
+                    found.push((id, hardware));
+                    continue;
+                };
+                match GammaController::for_output(output) {
+                    Ok(gamma) => found.push((id, Box::new(ExtendedRangeController::new(hardware, gamma, config.hardware_floor)))),
+                    Err(e) => {
+                        info!("Gamma dimming unavailable on {}: {}", output, e);
+                        found.push((id, hardware));
+                    }
+                }
+            }
+        }
+
+        for output in gamma::uncovered_outputs(outputs, &connectors, &ids) {
+            let id = format!("gamma:{}", output);
+            if held.contains(&id) {
+                continue;
+            }
+            found.extend(select_backend(&id, vec![ProbeCandidate::new("gamma LUT", GammaController::for_output(&output))]));
+        }
+    }
+
+    pub fn add(&mut self, managed: ManagedDisplay) {
+        info!("Managing display '{}' via {} (offset {:+.1}, scale {:.2}, {:.0}Hz)",
+              managed.id, managed.controller.name(), managed.offset, managed.scale, managed.controller.max_update_hz());
+        self.displays.push(managed);
+    }
+
+    // A display that appears while running starts where it already is (or, if
+    // unreadable, no higher than HOTPLUG_START_BRIGHTNESS) and ramps to the
+    // shared target like a resume instead of jumping to it
+    pub fn attach(&mut self, mut managed: ManagedDisplay, master: &EpilepsyGuard) -> Result<(), HardwareError> {
+        let shared = master.transition.as_ref().map_or(master.current_brightness, |t| t.target_brightness);
+        let target = managed.map(shared);
+        managed.guard.mode = master.mode;
+        managed.guard.set_mode_caps(master.safe_mode_brightness, master.sleep_mode_brightness);
+
+        let result = match managed.controller.get_brightness() {
+            Ok(current) => {
+                managed.guard.current_brightness = current;
+                Ok(())
+            }
+            Err(_) => {
+                let start = target.min(HOTPLUG_START_BRIGHTNESS);
+                managed.guard.current_brightness = start;
+                managed.controller.set_brightness(start)
+            }
+        };
+
+        let easing = managed.guard.easing;
+        let distance = target - managed.guard.current_brightness;
+        let duration = Duration::from_millis(RESUME_RAMP_MS).max(managed.scheduler.min_duration(distance, easing));
+        managed.start_transition(target, duration, easing);
+        // Only a new master transition replaces the ramp-in
+        managed.followed = master.transition.as_ref().map(|t| t.start_time);
+        info!("Display '{}' attached at {:.1}%, ramping to {:.1}% over {:.1}s",
+              managed.id, managed.guard.current_brightness, target, duration.as_secs_f64());
+        self.add(managed);
+        result
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<ManagedDisplay> {
+        let index = self.displays.iter().position(|d| d.id == id)?;
+        info!("Display '{}' removed", id);
+        Some(self.displays.remove(index))
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.displays.iter().map(|d| d.id.clone()).collect()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.displays.iter().any(|d| d.id == id)
+    }
+
+    // Swaps in a rebuilt backend (device node re-created), keeping the guard
+    // and with it the display's place in any running ramp
+    pub fn replace_controller(&mut self, id: &str, controller: BoxedController) -> bool {
+        let Some(d) = self.displays.iter_mut().find(|d| d.id == id) else { return false };
+        info!("Display '{}' rebuilt on {}", id, controller.name());
+        d.scheduler = UpdateScheduler::new(controller.max_update_hz());
+        d.controller = controller;
+        true
+    }
+
+    pub fn displays(&self) -> &[ManagedDisplay] {
+        &self.displays
+    }
+
+    pub fn len(&self) -> usize {
+        self.displays.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.displays.is_empty()
+    }
+
+    // Active backend of every display and why it was chosen
+    pub fn backend_status(&self) -> Vec<BackendStatus> {
+        self.displays.iter().map(|d| {
+            let mut status = d.controller.status().unwrap_or_else(|| BackendStatus {
+                display: String::new(),
+                backend: d.controller.name().to_string(),
+                reason: "only backend".to_string(),
+                failures: 0,
+                standby: Vec::new(),
+            });
+            status.display = d.id.clone();
+            status
+        }).collect()
+    }
+
+    // Whether any display dims through the gamma LUT (alone or extending a backlight)
+    pub fn uses_gamma(&self) -> bool {
+        self.displays.iter().any(|d| d.controller.name().contains("Gamma"))
+    }
+
+    // Immediate write of the shared value, only for startup restore
+    pub fn set_all(&mut self, value: f64) -> Vec<(String, HardwareError)> {
+        let mut errors = Vec::new();
+        for d in &mut self.displays {
+            let mapped = d.map(value);
+            d.guard.current_brightness = mapped;
+            if let Err(e) = d.controller.set_brightness(mapped) {
+                errors.push((d.id.clone(), e));
+            }
+        }
+        errors
+    }
+
+    // Mirrors the master guard's mode and starts a matching transition on every
+    // display guard whenever the master begins a new one
+    pub fn follow(&mut self, master: &EpilepsyGuard) {
+        let now = master.clock().now();
+        for d in &mut self.displays {
+            d.guard.mode = master.mode;
+            d.guard.set_mode_caps(master.safe_mode_brightness, master.sleep_mode_brightness);
+            match master.transition {
+                Some(ref trans) if d.followed != Some(trans.start_time) => {
+                    d.followed = Some(trans.start_time);
+                    let remaining = trans.duration.saturating_sub(now.duration_since(trans.start_time));
+                    let target = d.map(trans.target_brightness);
+                    // Slow backends get a longer ramp rather than bigger jumps
+                    let distance = target - d.guard.current_brightness;
+                    let duration = remaining.max(d.scheduler.min_duration(distance, trans.easing));
+                    d.start_transition(target, duration, trans.easing);
+                }
+                Some(_) => {}
+                None => d.followed = None,
+            }
+        }
+    }
+
+    // Advances every display guard and writes the samples each device can take,
+    // or the next planned step (or single level) on a coarse one. The final value of a transition
+    // is always written, on a later tick if the backend held it back.
+    pub fn tick(&mut self) -> Vec<(String, HardwareError)> {
+        let mut errors = Vec::new();
+        for d in &mut self.displays {
+            let Some(mut value) = d.guard.tick_transition().or_else(|| d.next_step()) else {
+                if let Err(e) = d.controller.flush() {
+                    errors.push((d.id.clone(), e));
+                }
+                continue;
+            };
+            d.scheduler.set_max_hz(d.controller.max_update_hz());
+            let started = d.guard.clock().now();
+            match &d.guard.transition {
+                Some(t) => {
+                    if !d.scheduler.is_due(started) {
+                        continue;
+                    }
+                    if let Some(plan) = d.plan.as_mut() {
+                        match plan.due(started.duration_since(t.start_time)) {
+                            Some(step) => value = step,
+                            None => continue,
+                        }
+                    }
+                }
+                None => d.plan = None,
+            }
+            let result = d.controller.set_brightness(value);
+            // Queued writes (DDC/CI) return at once; the device's own time counts
+            let latency = d.controller.write_latency().unwrap_or_else(|| d.guard.clock().now().duration_since(started));
+            d.scheduler.record_latency(started, latency);
+            if let Err(e) = result {
+                errors.push((d.id.clone(), e));
+            }
+        }
+        errors
+    }
+}
 
 pub struct DdcUtilController {
     display_id: u8,
@@ -32,6 +861,27 @@ impl DdcUtilController {
     pub fn new(display_id: u8) -> Self {
         Self { display_id }
     }
+
+    // Display numbers and i2c buses reported by `ddcutil detect --brief`
+    pub fn detect() -> Vec<(u8, Option<String>)> {
+        match Command::new("ddcutil").args(["detect", "--brief"]).output() {
+            Ok(o) if o.status.success() => parse_ddcutil_detect(&String::from_utf8_lossy(&o.stdout)),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// "Display 1" starts an entry, its "I2C bus: /dev/i2c-4" line names the bus ("i2c-4")
+pub fn parse_ddcutil_detect(output: &str) -> Vec<(u8, Option<String>)> {
+    let mut displays: Vec<(u8, Option<String>)> = Vec::new();
+    for line in output.lines().map(str::trim) {
+        if let Some(number) = line.strip_prefix("Display ").and_then(|n| n.trim().parse().ok()) {
+            displays.push((number, None));
+        } else if let (Some(bus), Some(last)) = (line.strip_prefix("I2C bus:"), displays.last_mut()) {
+            last.1 = bus.trim().rsplit('/').next().map(str::to_string);
+        }
+    }
+    displays
 }
 
 impl BrightnessController for DdcUtilController {
@@ -73,6 +923,21 @@ impl BrightnessController for DdcUtilController {
     fn name(&self) -> &str {
         "DDC/CI"
     }
+
+    // Every write spawns ddcutil, which takes a few hundred ms
+    fn max_update_hz(&self) -> f64 {
+        2.0
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: true,
+            resolution: 101,
+            latency: Duration::from_millis(300),
+            shows_osd: false,
+            privilege: Privilege::I2cGroup,
+        }
+    }
 }
 
 pub struct BacklightController {
@@ -82,7 +947,11 @@ pub struct BacklightController {
 
 impl BacklightController {
     pub fn new(name: &str) -> Result<Self, HardwareError> {
-        let base = PathBuf::from("/sys/class/backlight").join(name);
+        Self::with_root(&SysfsRoot::default(), name)
+    }
+
+    pub fn with_root(sysfs: &SysfsRoot, name: &str) -> Result<Self, HardwareError> {
+        let base = sysfs.backlight(name);
         if !base.exists() {
              return Err(HardwareError::NotSupported);
         }
@@ -106,18 +975,22 @@ impl BacklightController {
     }
     
     pub fn auto() -> Result<Self, HardwareError> {
-        let entries = fs::read_dir("/sys/class/backlight")?;
-        for entry in entries {
-            let entry = entry?;
-            let name = entry.file_name();
-            if let Some(name_str) = name.to_str() {
-                // Try to initialize. New logic will fail if not writable.
-                if let Ok(c) = Self::new(name_str) {
-                    return Ok(c);
-                }
-            }
-        }
-        Err(HardwareError::NotSupported)
+        let sysfs = SysfsRoot::default();
+        // Try to initialize. New logic will fail if not writable.
+        sysfs.backlights().iter()
+            .find_map(|name| Self::with_root(&sysfs, name).ok())
+            .ok_or(HardwareError::NotSupported)
+    }
+
+    // sysfs backlights only, no bus or desktop services: what --dry-run drives
+    // when pointed at a fake tree
+    pub fn discover(sysfs: &SysfsRoot) -> Vec<(String, BoxedController)> {
+        sysfs.backlights().into_iter()
+            .filter_map(|name| {
+                let controller = Self::with_root(sysfs, &name).ok()?;
+                Some((format!("backlight:{}", name), Box::new(controller) as BoxedController))
+            })
+            .collect()
     }
 }
 
@@ -142,6 +1015,109 @@ impl BrightnessController for BacklightController {
     fn name(&self) -> &str {
         "Backlight (sysfs)"
     }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: true,
+            resolution: self.max_brightness as u32 + 1,
+            latency: Duration::from_millis(1),
+            shows_osd: false,
+            privilege: Privilege::VideoGroup,
+        }
+    }
+}
+
+// Writes the backlight through systemd-logind, which allows the active session's
+// user without the udev rule or group membership BacklightController needs.
+// Reads still come from sysfs, which is world readable.
+pub struct LogindBacklightController {
+    connection: zbus::blocking::Connection,
+    device_name: String,
+    device_path: PathBuf,
+    max_brightness: f64,
+}
+
+impl LogindBacklightController {
+    pub fn new(name: &str) -> Result<Self, HardwareError> {
+        Self::with_root(&SysfsRoot::default(), name)
+    }
+
+    pub fn with_root(sysfs: &SysfsRoot, name: &str) -> Result<Self, HardwareError> {
+        let base = sysfs.backlight(name);
+        if !base.exists() {
+             return Err(HardwareError::NotSupported);
+        }
+        let max_str = fs::read_to_string(base.join("max_brightness"))?;
+        let max_brightness = max_str.trim().parse::<f64>().map_err(|_| HardwareError::NotSupported)?;
+
+        let connection = zbus::blocking::Connection::system()
+            .map_err(|e| HardwareError::Io(std::io::Error::other(e.to_string())))?;
+        let controller = Self {
+            connection,
+            device_name: name.to_string(),
+            device_path: base,
+            max_brightness,
+        };
+
+        // Rewrite the current value: fails unless this is the active local session
+        let raw = controller.read_raw()?;
+        controller.write_raw(raw).map_err(|e| {
+            warn!("logind refused backlight '{}': {}", name, e);
+            HardwareError::NotSupported
+        })?;
+        Ok(controller)
+    }
+
+    // Percent to the raw level logind is asked for, nearest level wins
+    pub(crate) fn raw_level(value: f64, max_brightness: f64) -> u32 {
+        (value.clamp(0.0, 100.0) / 100.0 * max_brightness).round() as u32
+    }
+
+    fn read_raw(&self) -> Result<u32, HardwareError> {
+        let content = fs::read_to_string(self.device_path.join("brightness"))?;
+        content.trim().parse::<u32>().map_err(|_| HardwareError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid brightness")))
+    }
+
+    fn write_raw(&self, raw: u32) -> Result<(), HardwareError> {
+        self.connection.call_method(
+            Some("org.freedesktop.login1"),
+            "/org/freedesktop/login1/session/auto",
+            Some("org.freedesktop.login1.Session"),
+            "SetBrightness",
+            &("backlight", self.device_name.as_str(), raw),
+        )
+        .map_err(|e| HardwareError::CommandFailed(format!("logind SetBrightness Error: {}", e)))?;
+        Ok(())
+    }
+}
+
+impl BrightnessController for LogindBacklightController {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        Ok(self.read_raw()? as f64 / self.max_brightness * 100.0)
+    }
+
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        self.write_raw(Self::raw_level(value, self.max_brightness))
+    }
+
+    fn name(&self) -> &str {
+        "Backlight (logind)"
+    }
+
+    // One system bus round trip per write
+    fn max_update_hz(&self) -> f64 {
+        60.0
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: true,
+            resolution: self.max_brightness as u32 + 1,
+            latency: Duration::from_millis(5),
+            shows_osd: false,
+            privilege: Privilege::SessionBus,
+        }
+    }
 }
 
 pub struct DummyController {
@@ -250,11 +1226,155 @@ impl BrightnessController for KdeBrightnessController {
     fn name(&self) -> &str {
         "KDE Plasma (Native DBus)"
     }
+
+    // Each call redraws the OSD
+    fn max_update_hz(&self) -> f64 {
+        10.0
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: true,
+            resolution: self.get_max().unwrap_or(100).max(0) as u32 + 1,
+            latency: Duration::from_millis(100),
+            shows_osd: true,
+            privilege: Privilege::SessionBus,
+        }
+    }
+}
+
+// gsd-power's Screen interface: Brightness is a 0 - 100 percent property,
+// -1 when the session has no controllable panel
+pub struct GnomeBrightnessController {
+    connection: zbus::blocking::Connection,
+}
+
+impl GnomeBrightnessController {
+    const DEST: &'static str = "org.gnome.SettingsDaemon.Power";
+    const PATH: &'static str = "/org/gnome/SettingsDaemon/Power";
+    const IFACE: &'static str = "org.gnome.SettingsDaemon.Power.Screen";
+
+    pub fn new() -> Result<Self, HardwareError> {
+        let connection = zbus::blocking::Connection::session()
+            .map_err(|e| HardwareError::Io(std::io::Error::other(e.to_string())))?;
+        let controller = Self { connection };
+
+        // Test connection by reading the property
+        match controller.get_brightness() {
+            Ok(_) => Ok(controller),
+            Err(_) => Err(HardwareError::NotSupported),
+        }
+    }
+
+    pub(crate) fn percent_from_property(value: i32) -> Result<f64, HardwareError> {
+        match value {
+            v if v < 0 => Err(HardwareError::NotSupported),
+            v => Ok(v as f64),
+        }
+    }
+
+    pub(crate) fn property_from_percent(value: f64) -> i32 {
+        value.clamp(0.0, 100.0).round() as i32
+    }
+
+    fn read_brightness(&self) -> Result<i32, HardwareError> {
+        use zbus::zvariant::OwnedValue;
+
+        let reply = self.connection.call_method(
+            Some(Self::DEST),
+            Self::PATH,
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &(Self::IFACE, "Brightness"),
+        )
+        .map_err(|e| HardwareError::CommandFailed(format!("DBus Properties.Get Error: {}", e)))?;
+
+        let value: OwnedValue = reply.body().deserialize()
+            .map_err(|e| HardwareError::CommandFailed(format!("Deserialize Error: {}", e)))?;
+        i32::try_from(value).map_err(|_| HardwareError::CommandFailed("Unexpected property type".into()))
+    }
+}
+
+impl BrightnessController for GnomeBrightnessController {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        Self::percent_from_property(self.read_brightness()?)
+    }
+
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        use zbus::zvariant::Value;
+
+        let target = Self::property_from_percent(value);
+        self.connection.call_method(
+            Some(Self::DEST),
+            Self::PATH,
+            Some("org.freedesktop.DBus.Properties"),
+            "Set",
+            &(Self::IFACE, "Brightness", Value::from(target)),
+        )
+        .map_err(|e| HardwareError::CommandFailed(format!("DBus Properties.Set Error: {}", e)))?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "GNOME Settings Daemon (DBus)"
+    }
+
+    // Whole percent steps through gsd-power, which then writes sysfs itself
+    fn max_update_hz(&self) -> f64 {
+        20.0
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: true,
+            resolution: 101,
+            latency: Duration::from_millis(50),
+            shows_osd: false,
+            privilege: Privilege::SessionBus,
+        }
+    }
+}
+
+// Display white point, driven through its own guard like brightness
+pub trait ColorTemperatureController {
+    fn get_kelvin(&self) -> Result<u32, HardwareError>;
+    fn set_kelvin(&mut self, kelvin: u32) -> Result<(), HardwareError>;
+    fn name(&self) -> &str;
+
+    fn max_update_hz(&self) -> f64 {
+        1.0
+    }
+
+    // Re-applies a value the desktop only holds for a while; called on ticks
+    // that bring no new value
+    fn refresh(&mut self) -> Result<(), HardwareError> {
+        Ok(())
+    }
+}
+
+pub type BoxedColorController = Box<dyn ColorTemperatureController + Send>;
+
+pub const MIN_KELVIN: u32 = 1000;
+pub const NEUTRAL_KELVIN: u32 = 6500;
+
+// The colour guard works on a 0 - 100 scale between MIN_KELVIN and NEUTRAL_KELVIN
+pub fn kelvin_to_level(kelvin: u32) -> f64 {
+    let k = kelvin.clamp(MIN_KELVIN, NEUTRAL_KELVIN) as f64;
+    (k - MIN_KELVIN as f64) / (NEUTRAL_KELVIN - MIN_KELVIN) as f64 * 100.0
+}
+
+pub fn level_to_kelvin(level: f64) -> u32 {
+    let k = MIN_KELVIN as f64 + level.clamp(0.0, 100.0) / 100.0 * (NEUTRAL_KELVIN - MIN_KELVIN) as f64;
+    k.round() as u32
 }
 
+// KWin drops a preview after 15 s, and the schedule's temperature comes back
+const KWIN_PREVIEW_REFRESH: Duration = Duration::from_secs(10);
+
 pub struct KdeNightLightController {
     connection: zbus::blocking::Connection,
     inhibit_cookie: std::sync::Mutex<Option<u32>>,
+    preview: std::sync::Mutex<Option<(u32, Instant)>>, // Kelvin being previewed and when it was last sent
 }
 
 impl KdeNightLightController {
@@ -276,9 +1396,38 @@ impl KdeNightLightController {
         
         Ok(Self { 
             connection,
-            inhibit_cookie: std::sync::Mutex::new(None)
+            inhibit_cookie: std::sync::Mutex::new(None),
+            preview: std::sync::Mutex::new(None),
         })
     }
+
+    fn send_preview(&self, kelvin: u32) -> Result<(), HardwareError> {
+        self.connection.call_method(
+            Some("org.kde.KWin"),
+            "/org/kde/KWin/NightLight",
+            Some("org.kde.KWin.NightLight"),
+            "preview",
+            &(kelvin),
+        )
+        .map_err(|e| HardwareError::CommandFailed(format!("NightLight DBus Error: {}", e)))?;
+        *self.preview.lock().map_err(|_| HardwareError::CommandFailed("Mutex Poisoned".into()))? = Some((kelvin, Instant::now()));
+        Ok(())
+    }
+
+    fn stop_preview(&self) {
+        if self.preview.lock().ok().and_then(|mut p| p.take()).is_none() {
+            return;
+        }
+        if let Err(e) = self.connection.call_method(
+            Some("org.kde.KWin"),
+            "/org/kde/KWin/NightLight",
+            Some("org.kde.KWin.NightLight"),
+            "stopPreview",
+            &(),
+        ) {
+            warn!("Failed to stop Night Light preview: {}", e);
+        }
+    }
     
     pub fn set_kelvin(&self, kelvin: u32) -> Result<(), HardwareError> {
         // Hybrid Control Strategy:
@@ -290,6 +1439,7 @@ impl KdeNightLightController {
         
         if kelvin >= 5500 {
             // Target is Day/Neutral
+            self.stop_preview();
             if cookie_opt.is_none() {
                  match self.connection.call_method(
                     Some("org.kde.KWin"),
@@ -323,18 +1473,8 @@ impl KdeNightLightController {
                 info!("Running Night Mode: Uninhibited Night Light");
             }
             
-            // Now set preview
-            let _ : () = self.connection.call_method(
-                Some("org.kde.KWin"),
-                "/org/kde/KWin/NightLight",
-                Some("org.kde.KWin.NightLight"),
-                "preview",
-                &(kelvin),
-            )
-            .map_err(|e| HardwareError::CommandFailed(format!("NightLight DBus Error: {}", e)))?
-            .body().deserialize()
-            .ok()
-            .unwrap_or(()); 
+            // Now set preview, kept alive by refresh
+            self.send_preview(kelvin)?;
         }
 
         Ok(())
@@ -386,5 +1526,277 @@ impl KdeNightLightController {
     }
 }
 
+impl ColorTemperatureController for KdeNightLightController {
+    fn get_kelvin(&self) -> Result<u32, HardwareError> {
+        self.get_current_kelvin()
+    }
+
+    fn set_kelvin(&mut self, kelvin: u32) -> Result<(), HardwareError> {
+        KdeNightLightController::set_kelvin(self, kelvin)
+    }
+
+    fn name(&self) -> &str {
+        KdeNightLightController::name(self)
+    }
 
+    fn max_update_hz(&self) -> f64 {
+        10.0
+    }
+
+    fn refresh(&mut self) -> Result<(), HardwareError> {
+        let preview = *self.preview.lock().map_err(|_| HardwareError::CommandFailed("Mutex Poisoned".into()))?;
+        match preview {
+            Some((kelvin, sent)) if sent.elapsed() >= KWIN_PREVIEW_REFRESH => self.send_preview(kelvin),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Hands the screen back to KWin's own schedule
+impl Drop for KdeNightLightController {
+    fn drop(&mut self) {
+        self.stop_preview();
+        if let Some(cookie) = self.inhibit_cookie.lock().ok().and_then(|mut c| c.take()) {
+            let _ = self.connection.call_method(
+                Some("org.kde.KWin"),
+                "/org/kde/KWin/NightLight",
+                Some("org.kde.KWin.NightLight"),
+                "uninhibit",
+                &(cookie),
+            );
+        }
+    }
+}
+
+// gsd-color's Night Light. Temperature is a setting, so it is written through
+// gsettings with an always-on manual schedule; the live value is read over DBus.
+// The user's own settings are read up front and put back on drop.
+pub struct GnomeNightLightController {
+    connection: zbus::blocking::Connection,
+    enabled: Option<bool>,
+    original: Vec<(&'static str, String)>,
+}
+
+impl GnomeNightLightController {
+    const SCHEMA: &'static str = "org.gnome.settings-daemon.plugins.color";
+
+    // Every key set_kelvin writes; night-light-enabled last so it is restored last
+    const KEYS: [&'static str; 5] = [
+        "night-light-schedule-automatic",
+        "night-light-schedule-from",
+        "night-light-schedule-to",
+        "night-light-temperature",
+        "night-light-enabled",
+    ];
+
+    pub fn new() -> Result<Self, HardwareError> {
+        let connection = zbus::blocking::Connection::session()
+            .map_err(|e| HardwareError::Io(std::io::Error::other(e.to_string())))?;
+        let original = Self::KEYS.into_iter()
+            .map(|key| Self::gsettings_get(key).map(|value| (key, value)))
+            .collect::<Result<_, _>>()
+            .map_err(|_| HardwareError::NotSupported)?;
+        let controller = Self { connection, enabled: None, original };
+        controller.get_kelvin().map_err(|_| HardwareError::NotSupported)?;
+        Ok(controller)
+    }
+
+    fn gsettings_get(key: &str) -> Result<String, HardwareError> {
+        let output = Command::new("gsettings").args(["get", Self::SCHEMA, key]).output()?;
+        if !output.status.success() {
+            return Err(HardwareError::CommandFailed(format!("gsettings get {} failed", key)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn gsettings_set(key: &str, value: &str) -> Result<(), HardwareError> {
+        let status = Command::new("gsettings").args(["set", Self::SCHEMA, key, value]).status()?;
+        if !status.success() {
+            return Err(HardwareError::CommandFailed(format!("gsettings set {} failed", key)));
+        }
+        Ok(())
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), HardwareError> {
+        if self.enabled == Some(enabled) {
+            return Ok(());
+        }
+        if enabled {
+            // from == to is a full-day schedule, so the daemon decides when it is night
+            Self::gsettings_set("night-light-schedule-automatic", "false")?;
+            Self::gsettings_set("night-light-schedule-from", "0.0")?;
+            Self::gsettings_set("night-light-schedule-to", "0.0")?;
+        }
+        Self::gsettings_set("night-light-enabled", if enabled { "true" } else { "false" })?;
+        self.enabled = Some(enabled);
+        Ok(())
+    }
+}
 
+impl ColorTemperatureController for GnomeNightLightController {
+    fn get_kelvin(&self) -> Result<u32, HardwareError> {
+        use zbus::zvariant::OwnedValue;
+
+        let reply = self.connection.call_method(
+            Some("org.gnome.SettingsDaemon.Color"),
+            "/org/gnome/SettingsDaemon/Color",
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &("org.gnome.SettingsDaemon.Color", "Temperature"),
+        )
+        .map_err(|e| HardwareError::CommandFailed(format!("DBus Properties.Get Error: {}", e)))?;
+
+        let value: OwnedValue = reply.body().deserialize()
+            .map_err(|e| HardwareError::CommandFailed(format!("Deserialize Error: {}", e)))?;
+        u32::try_from(value).map_err(|_| HardwareError::CommandFailed("Unexpected property type".into()))
+    }
+
+    fn set_kelvin(&mut self, kelvin: u32) -> Result<(), HardwareError> {
+        if kelvin >= NEUTRAL_KELVIN {
+            return self.set_enabled(false);
+        }
+        Self::gsettings_set("night-light-temperature", &kelvin.max(MIN_KELVIN).to_string())?;
+        self.set_enabled(true)
+    }
+
+    fn name(&self) -> &str {
+        "GNOME Night Light"
+    }
+}
+
+impl Drop for GnomeNightLightController {
+    fn drop(&mut self) {
+        // Nothing was written, so nothing the user changed since gets overwritten
+        if self.enabled.is_none() {
+            return;
+        }
+        for (key, value) in &self.original {
+            if let Err(e) = Self::gsettings_set(key, value) {
+                warn!("Failed to restore {}: {}", key, e);
+            }
+        }
+    }
+}
+
+// One-shot gammastep / redshift runs. Not used on Wayland: there the process has
+// to stay alive to hold the ramps, and restarting it flashes back to neutral.
+pub struct GammastepController {
+    program: &'static str,
+    kelvin: u32,
+}
+
+impl GammastepController {
+    pub fn new() -> Result<Self, HardwareError> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return Err(HardwareError::NotSupported);
+        }
+        ["gammastep", "redshift"]
+            .into_iter()
+            .find(|program| Command::new(program).arg("-V").output().is_ok_and(|o| o.status.success()))
+            .map(|program| Self { program, kelvin: NEUTRAL_KELVIN })
+            .ok_or(HardwareError::NotSupported)
+    }
+}
+
+impl ColorTemperatureController for GammastepController {
+    fn get_kelvin(&self) -> Result<u32, HardwareError> {
+        Ok(self.kelvin)
+    }
+
+    fn set_kelvin(&mut self, kelvin: u32) -> Result<(), HardwareError> {
+        // -P replaces the current ramps instead of stacking on them
+        let output = Command::new(self.program).args(["-P", "-O", &kelvin.to_string()]).output()?;
+        if !output.status.success() {
+            return Err(HardwareError::CommandFailed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        self.kelvin = kelvin;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        self.program
+    }
+}
+
+// The colour temperature output: a guard in kelvin level space (see
+// kelvin_to_level) so white point changes get the same eased, rate limited
+// transitions as brightness.
+pub struct ManagedColorTemperature {
+    pub controller: BoxedColorController,
+    pub guard: EpilepsyGuard,
+    pub scheduler: UpdateScheduler,
+}
+
+// White point shifts are slow by nature, a minute hides them completely
+const KELVIN_TRANSITION_MS: u64 = 60_000;
+
+// Smaller drifts of the schedule wait for the next request
+const KELVIN_DEAD_BAND: u32 = 100;
+
+impl ManagedColorTemperature {
+    pub fn new(controller: BoxedColorController, clock: SharedClock) -> Self {
+        let initial = controller.get_kelvin().unwrap_or(NEUTRAL_KELVIN);
+        let mut guard = EpilepsyGuard::with_clock(kelvin_to_level(initial), clock);
+        guard.set_space(BrightnessSpace::Linear);
+        let scheduler = UpdateScheduler::new(controller.max_update_hz());
+        Self { controller, guard, scheduler }
+    }
+
+    // "auto" picks the desktop's own night light, then gammastep / redshift.
+    // Skipped when the gamma brightness backend owns the LUT.
+    pub fn discover(method: &str, gamma_in_use: bool) -> Option<BoxedColorController> {
+        let kde = || KdeNightLightController::new().ok().map(|c| Box::new(c) as BoxedColorController);
+        let gnome = || GnomeNightLightController::new().ok().map(|c| Box::new(c) as BoxedColorController);
+        let gammastep = || {
+            if gamma_in_use {
+                warn!("gammastep would fight the gamma brightness backend over the LUT, colour control disabled");
+                return None;
+            }
+            GammastepController::new().ok().map(|c| Box::new(c) as BoxedColorController)
+        };
+        match method {
+            "kde" => kde(),
+            "gnome" => gnome(),
+            "gammastep" | "redshift" => gammastep(),
+            "auto" if is_kde_session() => kde().or_else(gammastep),
+            "auto" if is_gnome_session() => gnome().or_else(gammastep),
+            "auto" => gammastep(),
+            _ => None,
+        }
+    }
+
+    pub fn kelvin(&self) -> u32 {
+        level_to_kelvin(self.guard.current_brightness)
+    }
+
+    pub fn request_kelvin(&mut self, kelvin: u32) {
+        let planned = self.guard.transition.as_ref().map_or(self.guard.current_brightness, |t| t.target_brightness);
+        if level_to_kelvin(planned).abs_diff(kelvin) < KELVIN_DEAD_BAND {
+            return;
+        }
+        info!("Colour temperature: {}K -> {}K via {}", self.kelvin(), kelvin, self.controller.name());
+        // User transition durations top out at 2s, far too quick for colour
+        self.guard.pending_ramp_ms = Some(KELVIN_TRANSITION_MS);
+        self.guard.request_transition(kelvin_to_level(kelvin));
+    }
+
+    // Only the emergency stop carries over: mode brightness caps mean nothing for colour
+    pub fn follow(&mut self, master: &EpilepsyGuard) {
+        self.guard.mode = if master.mode == SafetyMode::EmergencyStop {
+            SafetyMode::EmergencyStop
+        } else {
+            SafetyMode::Automatic
+        };
+    }
+
+    pub fn tick(&mut self) -> Result<(), HardwareError> {
+        let Some(level) = self.guard.tick_transition() else { return self.controller.refresh() };
+        let started = self.guard.clock().now();
+        if self.guard.transition.is_some() && !self.scheduler.is_due(started) {
+            return Ok(());
+        }
+        let result = self.controller.set_kelvin(level_to_kelvin(level));
+        self.scheduler.record_write(started, self.guard.clock().now());
+        result
+    }
+}
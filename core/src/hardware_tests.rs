@@ -0,0 +1,384 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::ManualClock;
+    use crate::epilepsy::{BrightnessSpace, EpilepsyGuard, SafetyMode, MAX_DELTA_PER_STEP};
+    use crate::hardware::{gnome_session, parse_ddcutil_detect, select_backend, BackendCapabilities, BrightnessController, ColorTemperatureController, DisplayRegistry, DummyController, GnomeBrightnessController, HardwareError, LogindBacklightController, ManagedColorTemperature, ManagedDisplay, Privilege, ProbeCandidate, SafetyAction, SafetyAuditor, MAX_SCHEDULED_STEP, NEUTRAL_KELVIN};
+    use crate::sysfs::SysfsRoot;
+    use chrono::Utc;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // Records every write, declares a slow update rate like ddcutil
+    struct SlowController {
+        writes: Arc<Mutex<Vec<f64>>>,
+    }
+
+    impl BrightnessController for SlowController {
+        fn get_brightness(&self) -> Result<f64, HardwareError> {
+            Ok(self.writes.lock().unwrap().last().copied().unwrap_or(0.0))
+        }
+
+        fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+            self.writes.lock().unwrap().push(value);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "Slow"
+        }
+
+        fn max_update_hz(&self) -> f64 {
+            2.0
+        }
+    }
+
+    // Queues writes like the DDC/CI worker: set_brightness returns at once and
+    // the bus time is reported afterwards
+    struct QueuedController {
+        writes: Arc<Mutex<Vec<f64>>>,
+        bus_time: Duration,
+    }
+
+    impl BrightnessController for QueuedController {
+        fn get_brightness(&self) -> Result<f64, HardwareError> {
+            Ok(self.writes.lock().unwrap().last().copied().unwrap_or(0.0))
+        }
+
+        fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+            self.writes.lock().unwrap().push(value);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "Queued"
+        }
+
+        fn write_latency(&self) -> Option<Duration> {
+            Some(self.bus_time)
+        }
+    }
+
+    // Declares fixed capabilities for the probe tests
+    struct DescribedController {
+        caps: BackendCapabilities,
+        name: &'static str,
+    }
+
+    impl BrightnessController for DescribedController {
+        fn get_brightness(&self) -> Result<f64, HardwareError> {
+            Ok(50.0)
+        }
+
+        fn set_brightness(&mut self, _value: f64) -> Result<(), HardwareError> {
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            self.caps.clone()
+        }
+    }
+
+    struct RecordingColor {
+        writes: Arc<Mutex<Vec<u32>>>,
+        refreshes: Arc<Mutex<u32>>,
+    }
+
+    impl ColorTemperatureController for RecordingColor {
+        fn get_kelvin(&self) -> Result<u32, HardwareError> {
+            Ok(self.writes.lock().unwrap().last().copied().unwrap_or(NEUTRAL_KELVIN))
+        }
+
+        fn set_kelvin(&mut self, kelvin: u32) -> Result<(), HardwareError> {
+            self.writes.lock().unwrap().push(kelvin);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "Recording"
+        }
+
+        fn refresh(&mut self) -> Result<(), HardwareError> {
+            *self.refreshes.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_auditor_allows_ramp() {
+        let mut auditor = SafetyAuditor::new(DummyController::new(), 5.0);
+        let events = auditor.events();
+
+        for i in 0..50 {
+            auditor.set_brightness(20.0 + i as f64).unwrap();
+        }
+        assert_eq!(auditor.get_brightness().unwrap(), 69.0);
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_auditor_blocks_oscillation() {
+        let mut auditor = SafetyAuditor::new(DummyController::new(), 5.0);
+        let events = auditor.events();
+
+        // Strobe between 20% and 80% as fast as possible
+        for i in 0..20 {
+            let value = if i % 2 == 0 { 20.0 } else { 80.0 };
+            auditor.set_brightness(value).unwrap();
+        }
+
+        // Once flattened, repeating the same jump changes nothing and is vetoed
+        auditor.set_brightness(80.0).unwrap();
+        auditor.set_brightness(80.0).unwrap();
+        assert!((auditor.get_brightness().unwrap() - 20.0).abs() < 5.0);
+
+        let log = events.lock().unwrap();
+        assert!(log.iter().any(|e| e.action == SafetyAction::Flattened));
+        assert!(log.iter().any(|e| e.action == SafetyAction::Vetoed));
+    }
+
+    #[test]
+    fn test_perceptual_mapping() {
+        let space = BrightnessSpace::Perceptual;
+        // 18.4% luminance is roughly L* 50
+        assert!((space.from_percent(18.4) - 50.0).abs() < 0.1);
+        for l in [0.0, 5.0, 8.0, 25.0, 75.0, 100.0] {
+            assert!((space.from_percent(space.to_percent(l)) - l).abs() < 1e-6);
+        }
+
+        // The guard's step limit is perceived change: small in percent near the
+        // dark end, where the eye is most sensitive
+        let mut guard = EpilepsyGuard::new(5.0);
+        let step = guard.calculate_next_step(100.0) - 5.0;
+        assert!(step > 0.0 && step < MAX_DELTA_PER_STEP, "{}", step);
+    }
+
+    #[test]
+    fn test_registry_follows_master() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut master = EpilepsyGuard::with_clock(50.0, clock.clone());
+        let mut registry = DisplayRegistry::new();
+        registry.add(ManagedDisplay::new("laptop".into(), Box::new(DummyController::new()), EpilepsyGuard::with_clock(50.0, clock.clone())));
+        let mut external = ManagedDisplay::new("external".into(), Box::new(DummyController::new()), EpilepsyGuard::with_clock(50.0, clock.clone()));
+        external.scale = 0.5;
+        external.offset = 10.0;
+        registry.add(external);
+
+        assert!(registry.set_all(40.0).is_empty());
+        master.current_brightness = 40.0;
+        assert_eq!(registry.displays()[1].controller.get_brightness().unwrap(), 30.0);
+
+        master.request_transition(80.0);
+        registry.follow(&master);
+        for _ in 0..10 {
+            clock.advance(Duration::from_millis(100));
+            master.tick_transition();
+            registry.follow(&master);
+            assert!(registry.tick().is_empty());
+        }
+        assert_eq!(registry.displays()[0].controller.get_brightness().unwrap(), 80.0);
+        assert_eq!(registry.displays()[1].controller.get_brightness().unwrap(), 50.0);
+
+        // Emergency stop reaches every display guard
+        master.emergency_stop(None);
+        master.request_transition(20.0);
+        registry.follow(&master);
+        assert!(registry.displays().iter().all(|d| d.guard.mode == SafetyMode::EmergencyStop && d.guard.transition.is_none()));
+    }
+
+    #[test]
+    fn test_scheduler_resamples_slow_backend() {
+        let clock = ManualClock::shared(Utc::now());
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let mut master = EpilepsyGuard::with_clock(20.0, clock.clone());
+        let mut registry = DisplayRegistry::new();
+        registry.add(ManagedDisplay::new(
+            "slow".into(),
+            Box::new(SlowController { writes: writes.clone() }),
+            EpilepsyGuard::with_clock(20.0, clock.clone()),
+        ));
+
+        // 750ms master ramp over 60 points; the 2Hz display is stretched to small steps
+        master.request_transition(80.0);
+        for _ in 0..1000 {
+            registry.follow(&master);
+            master.tick_transition();
+            registry.tick();
+            clock.advance(Duration::from_millis(8));
+        }
+
+        let writes = writes.lock().unwrap();
+        assert_eq!(*writes.last().unwrap(), 80.0);
+        assert!(writes.len() >= 6 && writes.len() <= 15, "{} writes", writes.len());
+        let mut previous = 20.0;
+        for w in writes.iter() {
+            assert!(w - previous <= MAX_SCHEDULED_STEP + 1e-9, "step {} -> {}", previous, w);
+            previous = *w;
+        }
+    }
+
+    #[test]
+    fn test_queued_writes_paced_by_device_latency() {
+        let clock = ManualClock::shared(Utc::now());
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let mut master = EpilepsyGuard::with_clock(20.0, clock.clone());
+        let mut registry = DisplayRegistry::new();
+        registry.add(ManagedDisplay::new(
+            "queued".into(),
+            Box::new(QueuedController { writes: writes.clone(), bus_time: Duration::from_millis(200) }),
+            EpilepsyGuard::with_clock(20.0, clock.clone()),
+        ));
+        let run = |registry: &mut DisplayRegistry, master: &mut EpilepsyGuard, target: f64| {
+            master.request_transition(target);
+            for _ in 0..2000 {
+                registry.follow(master);
+                master.tick_transition();
+                registry.tick();
+                clock.advance(Duration::from_millis(8));
+            }
+        };
+
+        // The instant return does not count as a 0ms write
+        run(&mut registry, &mut master, 25.0);
+        assert_eq!(registry.displays()[0].scheduler.latency(), Some(Duration::from_millis(200)));
+        assert!(registry.displays()[0].scheduler.interval() >= Duration::from_millis(200));
+
+        // Later ramps are planned for the device's real pace: small steps, no catch-up jump
+        let before = writes.lock().unwrap().len();
+        run(&mut registry, &mut master, 80.0);
+        let writes = writes.lock().unwrap();
+        assert_eq!(*writes.last().unwrap(), 80.0);
+        let mut previous = 25.0;
+        for w in &writes[before..] {
+            assert!(w - previous <= MAX_SCHEDULED_STEP + 1e-9, "step {} -> {}", previous, w);
+            previous = *w;
+        }
+    }
+
+    #[test]
+    fn test_color_temperature_transition() {
+        let clock = ManualClock::shared(Utc::now());
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let refreshes = Arc::new(Mutex::new(0));
+        let mut color = ManagedColorTemperature::new(Box::new(RecordingColor { writes: writes.clone(), refreshes: refreshes.clone() }), clock.clone());
+        let master = EpilepsyGuard::with_clock(50.0, clock.clone());
+
+        color.request_kelvin(3400);
+        // Schedule drift inside the dead band keeps the running transition
+        let start = color.guard.transition.as_ref().unwrap().start_time;
+        clock.advance(Duration::from_secs(1));
+        color.request_kelvin(3450);
+        assert_eq!(color.guard.transition.as_ref().unwrap().start_time, start);
+
+        for _ in 0..700 {
+            color.follow(&master);
+            color.tick().unwrap();
+            clock.advance(Duration::from_millis(100));
+        }
+
+        // Eased over a minute at the backend's 1Hz, never jumping back up
+        let written = writes.lock().unwrap().clone();
+        assert_eq!(*written.last().unwrap(), 3400);
+        assert!(written.len() >= 50 && written.len() <= 65, "{} writes", written.len());
+        assert!(written.windows(2).all(|w| w[1] <= w[0]));
+        assert_eq!(color.kelvin(), 3400);
+
+        // Once settled every tick lets the backend re-apply what it holds
+        let settled = *refreshes.lock().unwrap();
+        for _ in 0..10 {
+            color.tick().unwrap();
+        }
+        assert_eq!(*refreshes.lock().unwrap(), settled + 10);
+        assert_eq!(writes.lock().unwrap().len(), written.len());
+    }
+
+    #[test]
+    fn test_scored_backend_selection() {
+        let sysfs = BackendCapabilities {
+            readback: true,
+            resolution: 1200,
+            latency: Duration::from_millis(1),
+            shows_osd: false,
+            privilege: Privilege::VideoGroup,
+        };
+        let desktop = BackendCapabilities {
+            resolution: 101,
+            latency: Duration::from_millis(100),
+            shows_osd: true,
+            privilege: Privilege::SessionBus,
+            ..sysfs.clone()
+        };
+        let ddcutil = BackendCapabilities { latency: Duration::from_millis(300), resolution: 101, ..sysfs.clone() };
+        assert!(sysfs.score() > desktop.score());
+        assert!(sysfs.score() > ddcutil.score());
+
+        // The OSD-popping desktop API loses to a working backlight, failures are skipped
+        let selected = select_backend("backlight:a", vec![
+            ProbeCandidate::new("desktop", Ok(DescribedController { caps: desktop.clone(), name: "desktop" })),
+            ProbeCandidate::new("sysfs", Err::<DescribedController, _>(HardwareError::NotSupported)),
+            ProbeCandidate::new("logind", Ok(DescribedController { caps: sysfs.clone(), name: "logind" })),
+        ]);
+        let (id, controller) = selected.unwrap();
+        assert_eq!(controller.name(), "logind");
+        assert!(controller.status().unwrap().reason.starts_with("logind scored highest"));
+
+        // The id is the output's, whichever backend wins
+        assert_eq!(id, "backlight:a");
+        let (id, controller) = select_backend("backlight:a", vec![
+            ProbeCandidate::new("desktop", Ok(DescribedController { caps: desktop.clone(), name: "desktop" })),
+        ]).unwrap();
+        assert_eq!((id.as_str(), controller.name()), ("backlight:a", "desktop"));
+
+        assert!(select_backend("none", vec![ProbeCandidate::new("sysfs", Err::<DescribedController, _>(HardwareError::NotSupported))]).is_none());
+    }
+
+    #[test]
+    fn test_parse_ddcutil_detect() {
+        let output = "Display 1\n   I2C bus:  /dev/i2c-4\n   Monitor: DEL:DELL U2720Q:ABC\n\nDisplay 2\n   Monitor: GSM:LG:123\n";
+        assert_eq!(parse_ddcutil_detect(output), vec![(1, Some("i2c-4".to_string())), (2, None)]);
+    }
+
+    #[test]
+    fn test_logind_levels_and_probe() {
+        // Nearest native level, clamped to the panel's range
+        assert_eq!(LogindBacklightController::raw_level(50.0, 255.0), 128);
+        assert_eq!(LogindBacklightController::raw_level(50.0, 7.0), 4);
+        assert_eq!(LogindBacklightController::raw_level(120.0, 7.0), 7);
+        assert_eq!(LogindBacklightController::raw_level(-5.0, 7.0), 0);
+        assert_eq!(LogindBacklightController::raw_level(100.0, 120000.0), 120000);
+
+        // Unusable backlights are turned down before the system bus is touched
+        let root = SysfsRoot::new(std::env::temp_dir().join(format!("epilyzer-logind-{}", std::process::id())));
+        assert!(matches!(LogindBacklightController::with_root(&root, "missing"), Err(HardwareError::NotSupported)));
+        let backlight = root.backlight("intel_backlight");
+        fs::create_dir_all(&backlight).unwrap();
+        fs::write(backlight.join("max_brightness"), "n/a\n").unwrap();
+        fs::write(backlight.join("brightness"), "0\n").unwrap();
+        assert!(matches!(LogindBacklightController::with_root(&root, "intel_backlight"), Err(HardwareError::NotSupported)));
+        fs::remove_dir_all(root.path()).ok();
+    }
+
+    #[test]
+    fn test_gnome_session_and_property_mapping() {
+        assert!(gnome_session(Some("GNOME"), None));
+        assert!(gnome_session(Some("ubuntu:GNOME"), None));
+        assert!(gnome_session(None, Some("gnome-xorg")));
+        assert!(!gnome_session(Some("KDE"), Some("plasma")));
+        // Only whole entries of the list count
+        assert!(!gnome_session(Some("GNOME-Flashback-ish"), None));
+        assert!(!gnome_session(None, None));
+
+        // gsd-power reports -1 when there is no panel it can drive
+        assert!(matches!(GnomeBrightnessController::percent_from_property(-1), Err(HardwareError::NotSupported)));
+        assert_eq!(GnomeBrightnessController::percent_from_property(0).unwrap(), 0.0);
+        assert_eq!(GnomeBrightnessController::percent_from_property(73).unwrap(), 73.0);
+        // Writes are whole percents inside the property's range
+        assert_eq!(GnomeBrightnessController::property_from_percent(42.6), 43);
+        assert_eq!(GnomeBrightnessController::property_from_percent(130.0), 100);
+        assert_eq!(GnomeBrightnessController::property_from_percent(-3.0), 0);
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+// Linux input event codes (linux/input-event-codes.h) for the modifiers
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_RIGHTCTRL: u16 = 97;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_RIGHTSHIFT: u16 = 54;
+const KEY_LEFTALT: u16 = 56;
+const KEY_RIGHTALT: u16 = 100;
+const KEY_LEFTMETA: u16 = 125;
+const KEY_RIGHTMETA: u16 = 126;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Ctrl,
+    Alt,
+    Shift,
+    Super,
+}
+
+impl Modifier {
+    fn codes(&self) -> [u16; 2] {
+        match self {
+            Modifier::Ctrl => [KEY_LEFTCTRL, KEY_RIGHTCTRL],
+            Modifier::Alt => [KEY_LEFTALT, KEY_RIGHTALT],
+            Modifier::Shift => [KEY_LEFTSHIFT, KEY_RIGHTSHIFT],
+            Modifier::Super => [KEY_LEFTMETA, KEY_RIGHTMETA],
+        }
+    }
+
+    // Modifier names used by the XDG GlobalShortcuts trigger syntax
+    fn portal_name(&self) -> &'static str {
+        match self {
+            Modifier::Ctrl => "CTRL",
+            Modifier::Alt => "ALT",
+            Modifier::Shift => "SHIFT",
+            Modifier::Super => "LOGO",
+        }
+    }
+}
+
+// A chord such as "Ctrl+Alt+B": any number of modifiers plus exactly one key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: Vec<Modifier>,
+    pub key: String, // Canonical name, e.g. "B", "F12", "Escape"
+    key_code: u16,
+}
+
+impl Hotkey {
+    pub fn key_code(&self) -> u16 {
+        self.key_code
+    }
+
+    // xkb keysym based trigger for the portal's preferred_trigger, e.g. "CTRL+ALT+b"
+    pub fn portal_trigger(&self) -> String {
+        let mut parts: Vec<String> = self.modifiers.iter().map(|m| m.portal_name().to_string()).collect();
+        let key = if self.key.len() == 1 { self.key.to_lowercase() } else { self.key.clone() };
+        parts.push(key);
+        parts.join("+")
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.modifiers {
+            write!(f, "{:?}+", m)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl std::str::FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Vec::new();
+        let mut key: Option<(String, u16)> = None;
+
+        for part in s.split('+').map(str::trim) {
+            if part.is_empty() {
+                return Err(format!("Invalid hotkey '{}': empty key name", s));
+            }
+            let modifier = match part.to_lowercase().as_str() {
+                "ctrl" | "control" => Some(Modifier::Ctrl),
+                "alt" => Some(Modifier::Alt),
+                "shift" => Some(Modifier::Shift),
+                "super" | "meta" | "win" | "logo" => Some(Modifier::Super),
+                _ => None,
+            };
+            if let Some(m) = modifier {
+                if modifiers.contains(&m) {
+                    return Err(format!("Invalid hotkey '{}': {:?} given twice", s, m));
+                }
+                modifiers.push(m);
+                continue;
+            }
+            if key.is_some() {
+                return Err(format!("Invalid hotkey '{}': more than one non-modifier key", s));
+            }
+            let code = key_code(part).ok_or_else(|| format!("Invalid hotkey '{}': unknown key '{}'", s, part))?;
+            key = Some((canonical_key_name(part), code));
+        }
+
+        let (key, key_code) = key.ok_or_else(|| format!("Invalid hotkey '{}': no key after the modifiers", s))?;
+        // A bare letter would fire while typing
+        let is_function_key = key.len() > 1 && key.starts_with('F');
+        if modifiers.is_empty() && !is_function_key && key != "Pause" {
+            return Err(format!("Invalid hotkey '{}': needs at least one modifier", s));
+        }
+        Ok(Hotkey { modifiers, key, key_code })
+    }
+}
+
+fn canonical_key_name(name: &str) -> String {
+    if name.len() == 1 {
+        return name.to_uppercase();
+    }
+    match name.to_lowercase().as_str() {
+        "esc" | "escape" => "Escape".to_string(),
+        "space" => "space".to_string(),
+        "pause" => "Pause".to_string(),
+        "delete" | "del" => "Delete".to_string(),
+        "home" => "Home".to_string(),
+        "end" => "End".to_string(),
+        _ => name.to_uppercase(), // F1 - F12
+    }
+}
+
+fn key_code(name: &str) -> Option<u16> {
+    const LETTERS: &str = "QWERTYUIOPASDFGHJKLZXCVBNM";
+    const LETTER_CODES: [u16; 26] = [
+        16, 17, 18, 19, 20, 21, 22, 23, 24, 25, // Q - P
+        30, 31, 32, 33, 34, 35, 36, 37, 38, // A - L
+        44, 45, 46, 47, 48, 49, 50, // Z - M
+    ];
+
+    let upper = name.to_uppercase();
+    if upper.len() == 1 {
+        let c = upper.chars().next()?;
+        if let Some(i) = LETTERS.find(c) {
+            return Some(LETTER_CODES[i]);
+        }
+        return match c {
+            '1'..='9' => Some(c as u16 - '1' as u16 + 2),
+            '0' => Some(11),
+            _ => None,
+        };
+    }
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<u16>().ok()) {
+        return match n {
+            1..=10 => Some(58 + n),
+            11 => Some(87),
+            12 => Some(88),
+            _ => None,
+        };
+    }
+    match upper.as_str() {
+        "ESC" | "ESCAPE" => Some(1),
+        "SPACE" => Some(57),
+        "PAUSE" => Some(119),
+        "DELETE" | "DEL" => Some(111),
+        "HOME" => Some(102),
+        "END" => Some(107),
+        _ => None,
+    }
+}
+
+// Tracks raw key events (evdev codes) and reports when the chord is completed.
+// Fires once per press of the main key, key repeat is ignored.
+#[derive(Debug, Clone)]
+pub struct ChordTracker {
+    hotkey: Hotkey,
+    pressed: HashSet<u16>,
+}
+
+impl ChordTracker {
+    pub fn new(hotkey: Hotkey) -> Self {
+        Self { hotkey, pressed: HashSet::new() }
+    }
+
+    // value follows evdev: 0 = release, 1 = press, 2 = repeat
+    pub fn feed(&mut self, code: u16, value: i32) -> bool {
+        match value {
+            0 => {
+                self.pressed.remove(&code);
+                false
+            }
+            1 => {
+                self.pressed.insert(code);
+                code == self.hotkey.key_code
+                    && self.hotkey.modifiers.iter().all(|m| m.codes().iter().any(|c| self.pressed.contains(c)))
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use crate::hotkey::{ChordTracker, Hotkey, Modifier};
+
+    #[test]
+    fn test_parse_hotkey() {
+        let hotkey: Hotkey = "Ctrl+Alt+B".parse().unwrap();
+        assert_eq!(hotkey.modifiers, vec![Modifier::Ctrl, Modifier::Alt]);
+        assert_eq!(hotkey.key, "B");
+        assert_eq!(hotkey.key_code(), 48);
+        assert_eq!(hotkey.portal_trigger(), "CTRL+ALT+b");
+
+        let hotkey: Hotkey = "super + shift + f12".parse().unwrap();
+        assert_eq!(hotkey.modifiers, vec![Modifier::Super, Modifier::Shift]);
+        assert_eq!(hotkey.key, "F12");
+        assert_eq!(hotkey.key_code(), 88);
+
+        assert!("F9".parse::<Hotkey>().is_ok());
+        assert!("B".parse::<Hotkey>().is_err());
+        assert!("Ctrl+Alt".parse::<Hotkey>().is_err());
+        assert!("Ctrl+A+B".parse::<Hotkey>().is_err());
+        assert!("Ctrl+Ctrl+B".parse::<Hotkey>().is_err());
+        assert!("Ctrl++B".parse::<Hotkey>().is_err());
+        assert!("Hyper+B".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn test_chord_tracker() {
+        let mut tracker = ChordTracker::new("Ctrl+Alt+B".parse().unwrap());
+
+        // B alone, or with only one modifier, does nothing
+        assert!(!tracker.feed(48, 1));
+        assert!(!tracker.feed(48, 0));
+        assert!(!tracker.feed(29, 1));
+        assert!(!tracker.feed(48, 1));
+        assert!(!tracker.feed(48, 0));
+
+        // Right Alt counts as Alt; fires on press, not on repeat
+        assert!(!tracker.feed(100, 1));
+        assert!(tracker.feed(48, 1));
+        assert!(!tracker.feed(48, 2));
+        assert!(!tracker.feed(48, 0));
+
+        // Releasing a modifier breaks the chord
+        assert!(!tracker.feed(29, 0));
+        assert!(!tracker.feed(48, 1));
+    }
+}
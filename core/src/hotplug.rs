@@ -0,0 +1,74 @@
+// Kernel / udev uevents as received on a NETLINK_KOBJECT_UEVENT socket
+
+// Subsystems whose events can add, remove or replace a display backend
+pub const DISPLAY_SUBSYSTEMS: [&str; 3] = ["backlight", "drm", "i2c-dev"];
+
+const UDEV_PREFIX: &[u8] = b"libudev\0";
+const UDEV_MAGIC: u32 = 0xfeed_cafe;
+const UDEV_HEADER_LEN: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uevent {
+    pub action: String, // "add", "remove", "change", "bind", ...
+    pub subsystem: String,
+    pub devpath: String,
+    pub devname: Option<String>,
+}
+
+impl Uevent {
+    pub fn sysname(&self) -> &str {
+        self.devpath.rsplit('/').next().unwrap_or("")
+    }
+
+    pub fn is_display_event(&self) -> bool {
+        DISPLAY_SUBSYSTEMS.contains(&self.subsystem.as_str())
+    }
+
+    // Id the registry gives the display behind this device, if it names one;
+    // drm connector events only say "something changed"
+    pub fn display_id(&self) -> Option<String> {
+        match self.subsystem.as_str() {
+            "backlight" => Some(format!("backlight:{}", self.sysname())),
+            "i2c-dev" => Some(format!("ddc:{}", self.sysname())),
+            _ => None,
+        }
+    }
+}
+
+// Accepts both the kernel format ("action@devpath\0KEY=VALUE\0...") and udev's
+// re-broadcast, which puts a binary header in front of the same properties
+pub fn parse_uevent(buf: &[u8]) -> Option<Uevent> {
+    let properties = if buf.starts_with(UDEV_PREFIX) {
+        if buf.len() < UDEV_HEADER_LEN {
+            return None;
+        }
+        let word = |at: usize| buf.get(at..at + 4).map(|b| [b[0], b[1], b[2], b[3]]);
+        if u32::from_be_bytes(word(8)?) != UDEV_MAGIC {
+            return None;
+        }
+        let offset = u32::from_ne_bytes(word(16)?) as usize;
+        let len = u32::from_ne_bytes(word(20)?) as usize;
+        buf.get(offset..offset.checked_add(len)?)?
+    } else {
+        // Skip the "action@devpath" summary, the properties repeat it
+        let summary_end = buf.iter().position(|b| *b == 0)?;
+        std::str::from_utf8(&buf[..summary_end]).ok()?.split_once('@')?;
+        &buf[summary_end + 1..]
+    };
+
+    let mut action = None;
+    let mut subsystem = None;
+    let mut devpath = None;
+    let mut devname = None;
+    for field in properties.split(|b| *b == 0) {
+        let Some((key, value)) = std::str::from_utf8(field).ok().and_then(|f| f.split_once('=')) else { continue };
+        match key {
+            "ACTION" => action = Some(value.to_string()),
+            "SUBSYSTEM" => subsystem = Some(value.to_string()),
+            "DEVPATH" => devpath = Some(value.to_string()),
+            "DEVNAME" => devname = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Some(Uevent { action: action?, subsystem: subsystem?, devpath: devpath?, devname })
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::ManualClock;
+    use crate::epilepsy::{EpilepsyGuard, RESUME_RAMP_MS};
+    use crate::hardware::{BrightnessController, DisplayRegistry, DummyController, HardwareError, ManagedDisplay, HOTPLUG_START_BRIGHTNESS};
+    use crate::hotplug::parse_uevent;
+    use chrono::Utc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // A DDC monitor that cannot be read back, records every write
+    struct WriteOnlyController {
+        writes: Arc<Mutex<Vec<f64>>>,
+    }
+
+    impl BrightnessController for WriteOnlyController {
+        fn get_brightness(&self) -> Result<f64, HardwareError> {
+            Err(HardwareError::NotSupported)
+        }
+
+        fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+            self.writes.lock().unwrap().push(value);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "write-only"
+        }
+    }
+
+    #[test]
+    fn test_parse_kernel_uevent() {
+        let msg = b"add@/devices/pci0000:00/0000:00:02.0/drm/card1/card1-eDP-1/intel_backlight\0\
+                    ACTION=add\0DEVPATH=/devices/pci0000:00/0000:00:02.0/drm/card1/card1-eDP-1/intel_backlight\0\
+                    SUBSYSTEM=backlight\0SEQNUM=4711\0";
+        let event = parse_uevent(msg).unwrap();
+        assert_eq!(event.action, "add");
+        assert_eq!(event.sysname(), "intel_backlight");
+        assert!(event.is_display_event());
+        assert_eq!(event.display_id().as_deref(), Some("backlight:intel_backlight"));
+
+        let usb = parse_uevent(b"add@/devices/usb1/1-1\0ACTION=add\0DEVPATH=/devices/usb1/1-1\0SUBSYSTEM=usb\0").unwrap();
+        assert!(!usb.is_display_event());
+        assert!(parse_uevent(b"garbage").is_none());
+    }
+
+    #[test]
+    fn test_parse_udev_uevent() {
+        let properties = b"ACTION=remove\0DEVPATH=/devices/pci0000:00/0000:00:02.0/i2c-7/i2c-dev/i2c-7\0\
+                           SUBSYSTEM=i2c-dev\0DEVNAME=/dev/i2c-7\0";
+        let mut msg = vec![0u8; 40];
+        msg[..8].copy_from_slice(b"libudev\0");
+        msg[8..12].copy_from_slice(&0xfeed_cafe_u32.to_be_bytes());
+        msg[16..20].copy_from_slice(&40u32.to_ne_bytes());
+        msg[20..24].copy_from_slice(&(properties.len() as u32).to_ne_bytes());
+        msg.extend_from_slice(properties);
+
+        let event = parse_uevent(&msg).unwrap();
+        assert_eq!(event.action, "remove");
+        assert_eq!(event.devname.as_deref(), Some("/dev/i2c-7"));
+        assert_eq!(event.display_id().as_deref(), Some("ddc:i2c-7"));
+
+        // Wrong magic or a properties block past the end is dropped
+        let mut bad = msg.clone();
+        bad[8] = 0;
+        assert!(parse_uevent(&bad).is_none());
+        msg[20..24].copy_from_slice(&(properties.len() as u32 + 1).to_ne_bytes());
+        assert!(parse_uevent(&msg).is_none());
+    }
+
+    #[test]
+    fn test_attached_display_ramps_in() {
+        let clock = ManualClock::shared(Utc::now());
+        let master = EpilepsyGuard::with_clock(80.0, clock.clone());
+        let mut registry = DisplayRegistry::new();
+
+        // Unreadable: starts at a safe level, never at the master's 80%
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let monitor = ManagedDisplay::new(
+            "ddc:i2c-7".into(),
+            Box::new(WriteOnlyController { writes: writes.clone() }),
+            EpilepsyGuard::with_clock(80.0, clock.clone()),
+        );
+        registry.attach(monitor, &master).unwrap();
+        assert_eq!(*writes.lock().unwrap(), vec![HOTPLUG_START_BRIGHTNESS]);
+
+        // Readable: starts where the device already is
+        let mut panel = DummyController::new();
+        panel.set_brightness(95.0).unwrap();
+        registry.attach(ManagedDisplay::new("backlight:panel".into(), Box::new(panel), EpilepsyGuard::with_clock(80.0, clock.clone())), &master).unwrap();
+        assert_eq!(registry.displays()[1].guard.current_brightness, 95.0);
+
+        for _ in 0..(RESUME_RAMP_MS / 100 + 10) {
+            clock.advance(Duration::from_millis(100));
+            registry.follow(&master);
+            assert!(registry.tick().is_empty());
+        }
+
+        // A guarded ramp: monotonic, small steps, lands on the master's level
+        let writes = writes.lock().unwrap();
+        assert!(writes.len() > 10);
+        assert!(writes.windows(2).all(|w| w[1] >= w[0] && w[1] - w[0] < 10.0), "{:?}", writes);
+        assert_eq!(*writes.last().unwrap(), 80.0);
+        assert_eq!(registry.displays()[1].controller.get_brightness().unwrap(), 80.0);
+    }
+}
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use crate::epilepsy::{Easing, SafetyMode};
+use crate::failover::BackendStatus;
+use crate::hardware::SafetyEvent;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum IpcCommand {
@@ -6,8 +9,13 @@ pub enum IpcCommand {
     SetWakeTime(u8, u8), // Hour, Minute
     SetTransitionDuration(u64), // Milliseconds
     SetFlashbangProtection(bool),
+    SetEasing(Easing),
+    SetFlashbangEasing(Easing),
+    SetMode(SafetyMode), // Automatic, Safe or Sleep (EmergencyStop = Freeze(0))
     GetInfo,
-    Freeze(u64), // Seconds
+    GetSafetyEvents,
+    Freeze(u64), // Seconds, 0 = until Resume
+    Resume,
     ResetAuto,
     Heartbeat,
 }
@@ -21,6 +29,23 @@ pub enum IpcResponse {
         wake_time: String,
         transition_duration_ms: u64,
         flashbang_protection: bool,
+        #[serde(default)]
+        safety_events: usize,
+        #[serde(default)]
+        easing: Easing,
+        #[serde(default)]
+        flashbang_easing: Easing,
+        #[serde(default)]
+        frozen: bool,
+        #[serde(default)]
+        freeze_remaining_secs: Option<u64>, // None while frozen = until resumed
+        #[serde(default)]
+        mode: SafetyMode,
+        #[serde(default)]
+        backends: Vec<BackendStatus>,
+        #[serde(default)]
+        ambient_lux: Option<f64>, // Smoothed, None without a light sensor
     },
+    SafetyEvents(Vec<SafetyEvent>),
     Error(String),
 }
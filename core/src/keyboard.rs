@@ -0,0 +1,195 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::ambient::interpolate_keyframes;
+use crate::clock::SharedClock;
+use crate::epilepsy::{BrightnessSpace, EpilepsyGuard, SafetyMode};
+use crate::hardware::{BackendCapabilities, BrightnessController, HardwareError, Privilege, UpdateScheduler};
+use crate::sysfs::SysfsRoot;
+
+// How far past the midpoint between two levels the value has to go before the
+// LED switches, as a share of one step. Keeps a target hovering at a boundary
+// from toggling a 3 level keyboard on and off.
+pub const LEVEL_HYSTERESIS: f64 = 0.25;
+
+// Keyboard percent for a given automatic screen target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KeyboardKeyframe {
+    pub screen: f64,
+    pub keyboard: f64,
+}
+
+// Full in the dark, half at dusk, off once the room is lit
+pub fn default_keyboard_curve() -> Vec<KeyboardKeyframe> {
+    [(10.0, 100.0), (35.0, 50.0), (60.0, 0.0)].iter()
+        .map(|&(screen, keyboard)| KeyboardKeyframe { screen, keyboard })
+        .collect()
+}
+
+// Keyframes (ascending screen target) interpolated linearly, flat beyond the ends
+pub fn keyboard_brightness(curve: &[KeyboardKeyframe], screen: f64) -> Option<f64> {
+    interpolate_keyframes(curve, screen, |k| (k.screen, k.keyboard), |x| x)
+}
+
+// Device level for `percent` on a 0..=max LED, currently at `current`
+pub fn quantize_level(percent: f64, max: u32, current: u32) -> u32 {
+    let exact = percent.clamp(0.0, 100.0) / 100.0 * max as f64;
+    if (exact - current as f64).abs() < 0.5 + LEVEL_HYSTERESIS {
+        return current.min(max);
+    }
+    exact.round() as u32
+}
+
+// A */*::kbd_backlight LED. Writes go to sysfs, or through logind (which
+// also handles the "leds" subsystem) when the file is not writable.
+pub struct LedController {
+    name: String,
+    device_path: PathBuf,
+    max_brightness: u32,
+    level: u32,
+    logind: Option<zbus::blocking::Connection>,
+}
+
+impl LedController {
+    pub fn new(sysfs: &SysfsRoot, name: &str) -> Result<Self, HardwareError> {
+        let base = sysfs.class("leds").join(name);
+        let max_brightness = fs::read_to_string(base.join("max_brightness"))?.trim().parse::<u32>()
+            .map_err(|_| HardwareError::NotSupported)?;
+        if max_brightness == 0 {
+            return Err(HardwareError::NotSupported);
+        }
+        let logind = if fs::OpenOptions::new().write(true).open(base.join("brightness")).is_ok() {
+            None
+        } else {
+            Some(zbus::blocking::Connection::system()
+                .map_err(|e| HardwareError::Io(std::io::Error::other(e.to_string())))?)
+        };
+        let mut controller = Self { name: name.to_string(), device_path: base, max_brightness, level: 0, logind };
+        controller.level = controller.read_level()?;
+        Ok(controller)
+    }
+
+    // The first keyboard backlight LED, if any
+    pub fn discover(sysfs: &SysfsRoot) -> Option<Self> {
+        let mut names: Vec<String> = fs::read_dir(sysfs.class("leds")).ok()?
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.ends_with("::kbd_backlight"))
+            .collect();
+        names.sort();
+        names.iter().find_map(|name| match Self::new(sysfs, name) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                warn!("Keyboard backlight '{}' unusable: {}", name, e);
+                None
+            }
+        })
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.max_brightness + 1
+    }
+
+    fn read_level(&self) -> Result<u32, HardwareError> {
+        fs::read_to_string(self.device_path.join("brightness"))?.trim().parse::<u32>()
+            .map_err(|_| HardwareError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid brightness")))
+    }
+
+    fn write_level(&self, level: u32) -> Result<(), HardwareError> {
+        match &self.logind {
+            None => fs::write(self.device_path.join("brightness"), level.to_string())?,
+            Some(connection) => {
+                connection.call_method(
+                    Some("org.freedesktop.login1"),
+                    "/org/freedesktop/login1/session/auto",
+                    Some("org.freedesktop.login1.Session"),
+                    "SetBrightness",
+                    &("leds", self.name.as_str(), level),
+                )
+                .map_err(|e| HardwareError::CommandFailed(format!("logind SetBrightness Error: {}", e)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BrightnessController for LedController {
+    fn get_brightness(&self) -> Result<f64, HardwareError> {
+        Ok(self.read_level()? as f64 / self.max_brightness as f64 * 100.0)
+    }
+
+    // Only touches the device when the quantized level changes
+    fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+        let level = quantize_level(value, self.max_brightness, self.level);
+        if level != self.level {
+            self.write_level(level)?;
+            self.level = level;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            readback: true,
+            resolution: self.levels(),
+            latency: Duration::from_millis(1),
+            shows_osd: false,
+            privilege: if self.logind.is_some() { Privilege::SessionBus } else { Privilege::VideoGroup },
+        }
+    }
+}
+
+// The keyboard backlight: follows the automatic screen target through its own
+// curve, with a guard of its own for the same eased, rate limited transitions
+pub struct ManagedKeyboard {
+    pub controller: LedController,
+    pub guard: EpilepsyGuard,
+    pub scheduler: UpdateScheduler,
+    curve: Vec<KeyboardKeyframe>,
+}
+
+impl ManagedKeyboard {
+    pub fn new(controller: LedController, curve: Vec<KeyboardKeyframe>, clock: SharedClock) -> Self {
+        let initial = controller.get_brightness().unwrap_or(0.0);
+        let mut guard = EpilepsyGuard::with_clock(initial, clock);
+        guard.set_space(BrightnessSpace::Linear);
+        let scheduler = UpdateScheduler::new(controller.max_update_hz());
+        Self { controller, guard, scheduler, curve }
+    }
+
+    pub fn request_target(&mut self, screen_target: f64) {
+        let target = keyboard_brightness(&self.curve, screen_target).unwrap_or(0.0).clamp(0.0, 100.0);
+        let planned = self.guard.transition.as_ref().map_or(self.guard.current_brightness, |t| t.target_brightness);
+        if (planned - target).abs() < 1.0 {
+            return;
+        }
+        info!("Keyboard backlight: {:.0}% -> {:.0}% ({} levels)", planned, target, self.controller.levels());
+        self.guard.request_transition(target);
+    }
+
+    // Only the emergency stop carries over, the screen's mode caps are not the keyboard's
+    pub fn follow(&mut self, master: &EpilepsyGuard) {
+        self.guard.mode = if master.mode == SafetyMode::EmergencyStop {
+            SafetyMode::EmergencyStop
+        } else {
+            SafetyMode::Automatic
+        };
+    }
+
+    pub fn tick(&mut self) -> Result<(), HardwareError> {
+        let Some(level) = self.guard.tick_transition() else { return Ok(()) };
+        let started = self.guard.clock().now();
+        if self.guard.transition.is_some() && !self.scheduler.is_due(started) {
+            return Ok(());
+        }
+        let result = self.controller.set_brightness(level);
+        self.scheduler.record_write(started, self.guard.clock().now());
+        result
+    }
+}
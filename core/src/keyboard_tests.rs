@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::ManualClock;
+    use crate::epilepsy::EpilepsyGuard;
+    use crate::keyboard::{default_keyboard_curve, keyboard_brightness, quantize_level, LedController, ManagedKeyboard};
+    use crate::sysfs::SysfsRoot;
+    use chrono::Utc;
+    use std::fs;
+    use std::time::Duration;
+
+    const LED: &str = "tpacpi::kbd_backlight";
+
+    fn fake_led(name: &str, max_brightness: u32) -> SysfsRoot {
+        let root = SysfsRoot::new(std::env::temp_dir().join(format!("epilyzer-leds-{}-{}", name, std::process::id())));
+        let led = root.class("leds").join(LED);
+        fs::create_dir_all(&led).unwrap();
+        fs::write(led.join("max_brightness"), format!("{}\n", max_brightness)).unwrap();
+        fs::write(led.join("brightness"), "0\n").unwrap();
+        fs::create_dir_all(root.class("leds").join("input3::capslock")).unwrap();
+        root
+    }
+
+    fn level(root: &SysfsRoot) -> u32 {
+        fs::read_to_string(root.class("leds").join(LED).join("brightness")).unwrap().trim().parse().unwrap()
+    }
+
+    #[test]
+    fn test_quantize_level_hysteresis() {
+        // 3 levels: 0, 1, 2. Leaving level 0 takes more than the rounding midpoint
+        assert_eq!(quantize_level(30.0, 2, 0), 0);
+        assert_eq!(quantize_level(40.0, 2, 0), 1);
+        // Hovering around 25% (the 0/1 midpoint) holds whichever level is current
+        for percent in [20.0, 28.0, 22.0, 30.0] {
+            assert_eq!(quantize_level(percent, 2, 1), 1);
+            assert_eq!(quantize_level(percent, 2, 0), 0);
+        }
+        assert_eq!(quantize_level(100.0, 2, 0), 2);
+        assert_eq!(quantize_level(0.0, 2, 2), 0);
+    }
+
+    #[test]
+    fn test_keyboard_curve() {
+        let curve = default_keyboard_curve();
+        assert_eq!(keyboard_brightness(&curve, 0.0), Some(100.0));
+        assert_eq!(keyboard_brightness(&curve, 22.5), Some(75.0));
+        assert_eq!(keyboard_brightness(&curve, 90.0), Some(0.0));
+        assert_eq!(keyboard_brightness(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_keyboard_follows_target() {
+        let root = fake_led("follow", 2);
+        let clock = ManualClock::shared(Utc::now());
+        let led = LedController::discover(&root).unwrap();
+        assert_eq!(led.levels(), 3);
+        let mut keyboard = ManagedKeyboard::new(led, default_keyboard_curve(), clock.clone());
+        let master = EpilepsyGuard::with_clock(50.0, clock.clone());
+
+        // Dark room: eased up through each level in turn
+        keyboard.request_target(10.0);
+        let mut seen = vec![level(&root)];
+        for _ in 0..300 {
+            clock.advance(Duration::from_millis(8));
+            keyboard.follow(&master);
+            keyboard.tick().unwrap();
+            if seen.last() != Some(&level(&root)) {
+                seen.push(level(&root));
+            }
+        }
+        assert_eq!(seen, vec![0, 1, 2]);
+
+        // Bright room: off again
+        keyboard.request_target(80.0);
+        for _ in 0..300 {
+            clock.advance(Duration::from_millis(8));
+            keyboard.tick().unwrap();
+        }
+        assert_eq!(level(&root), 0);
+        fs::remove_dir_all(root.path()).ok();
+    }
+}
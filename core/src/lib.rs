@@ -4,10 +4,42 @@ pub mod config;
 
 pub mod ipc;
 pub mod context;
+pub mod clock;
+pub mod hotkey;
+pub mod ddc;
+pub mod gamma;
+pub mod failover;
+pub mod hotplug;
+pub mod ambient;
+pub mod sysfs;
+pub mod keyboard;
+pub mod transition;
 
 
 
 
 #[cfg(test)]
 mod epilepsy_tests;
+#[cfg(test)]
+mod hardware_tests;
+#[cfg(test)]
+mod config_tests;
+#[cfg(test)]
+mod hotkey_tests;
+#[cfg(test)]
+mod ddc_tests;
+#[cfg(test)]
+mod gamma_tests;
+#[cfg(test)]
+mod failover_tests;
+#[cfg(test)]
+mod hotplug_tests;
+#[cfg(test)]
+mod ambient_tests;
+#[cfg(test)]
+mod sysfs_tests;
+#[cfg(test)]
+mod keyboard_tests;
+#[cfg(test)]
+mod transition_tests;
 mod debug_test;
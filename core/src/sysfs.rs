@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Points every sysfs reader at another tree, e.g. a fake one for tests or --dry-run demos
+pub const SYSFS_ROOT_ENV: &str = "EPILYZER_SYSFS_ROOT";
+
+const SYSTEM_ROOT: &str = "/sys";
+
+// Where sysfs is mounted. The hardware and power layer resolve every
+// /sys path through this, so the whole tree can be swapped out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysfsRoot {
+    path: PathBuf,
+}
+
+impl SysfsRoot {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn system() -> Self {
+        Self::new(SYSTEM_ROOT)
+    }
+
+    // A config value wins over EPILYZER_SYSFS_ROOT, which wins over /sys
+    pub fn resolve(configured: Option<&Path>) -> Self {
+        match configured {
+            Some(path) => Self::new(path),
+            None => std::env::var_os(SYSFS_ROOT_ENV).map_or_else(Self::system, Self::new),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_system(&self) -> bool {
+        self.path == Path::new(SYSTEM_ROOT)
+    }
+
+    pub fn class(&self, class: &str) -> PathBuf {
+        self.path.join("class").join(class)
+    }
+
+    pub fn backlight(&self, name: &str) -> PathBuf {
+        self.class("backlight").join(name)
+    }
+
+    pub fn backlights(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(self.class("backlight"))
+            .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    // Connectors of every card, sorted by name
+    pub fn drm_connectors(&self) -> Vec<DrmConnector> {
+        let backlights = self.backlights();
+        let Ok(entries) = fs::read_dir(self.class("drm")) else { return Vec::new() };
+        let mut connectors: Vec<DrmConnector> = entries.flatten().filter_map(|entry| {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            // "card0-DP-1"; the card itself and render nodes are not connectors
+            let (card, name) = dir_name.split_once('-')?;
+            if !card.starts_with("card") {
+                return None;
+            }
+            let path = entry.path();
+            let connected = fs::read_to_string(path.join("status")).is_ok_and(|s| s.trim() == "connected");
+            // The ddc link, or (amdgpu) the DP AUX adapter registered under the connector
+            let ddc_bus = fs::read_link(path.join("ddc")).ok()
+                .and_then(|link| link.file_name().map(|n| n.to_string_lossy().to_string()))
+                .or_else(|| fs::read_dir(&path).ok()?.flatten()
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .find(|n| n.starts_with("i2c-")));
+            let backlights = backlights.iter().filter(|b| path.join(b).exists()).cloned().collect();
+            Some(DrmConnector { name: name.to_string(), connected, ddc_bus, backlights })
+        }).collect();
+        connectors.sort_by(|a, b| a.name.cmp(&b.name));
+        connectors
+    }
+
+    pub fn iio_devices(&self) -> PathBuf {
+        self.path.join("bus").join("iio").join("devices")
+    }
+}
+
+// One DRM connector (/sys/class/drm/card0-DP-1), named without the card the
+// way the kernel and Wayland compositors name outputs ("DP-1", "HDMI-A-1")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrmConnector {
+    pub name: String,
+    pub connected: bool,
+    pub ddc_bus: Option<String>, // i2c adapter on the monitor's DDC lines ("i2c-5")
+    pub backlights: Vec<String>, // Backlight devices registered under the connector
+}
+
+impl Default for SysfsRoot {
+    fn default() -> Self {
+        Self::resolve(None)
+    }
+}
+
+// True while any battery reports it is discharging
+pub fn is_on_battery(root: &SysfsRoot) -> bool {
+    let Ok(entries) = fs::read_dir(root.class("power_supply")) else { return false };
+    entries.flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("BAT"))
+        .any(|e| fs::read_to_string(e.path().join("status")).is_ok_and(|s| s.trim() == "Discharging"))
+}
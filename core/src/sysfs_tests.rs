@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::gamma::GammaMode;
+    use crate::hardware::{BacklightController, BrightnessController, DisplayRegistry};
+    use crate::sysfs::{is_on_battery, DrmConnector, SysfsRoot};
+    use std::fs;
+    use std::path::Path;
+
+    // A throwaway /sys with one backlight and one battery
+    fn fake_sysfs(name: &str, max_brightness: u32, battery_status: &str) -> SysfsRoot {
+        let root = SysfsRoot::new(std::env::temp_dir().join(format!("epilyzer-sysfs-{}-{}", name, std::process::id())));
+        let backlight = root.backlight("acpi_video0");
+        fs::create_dir_all(&backlight).unwrap();
+        fs::write(backlight.join("max_brightness"), format!("{}\n", max_brightness)).unwrap();
+        fs::write(backlight.join("brightness"), "0\n").unwrap();
+        let battery = root.class("power_supply").join("BAT0");
+        fs::create_dir_all(&battery).unwrap();
+        fs::write(battery.join("status"), format!("{}\n", battery_status)).unwrap();
+        fs::create_dir_all(root.class("power_supply").join("AC")).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_backlight_in_fake_tree() {
+        let root = fake_sysfs("backlight", 255, "Charging");
+        let mut found = BacklightController::discover(&root);
+        assert_eq!(found.len(), 1);
+        let (id, controller) = &mut found[0];
+        assert_eq!(id, "backlight:acpi_video0");
+
+        controller.set_brightness(50.0).unwrap();
+        let raw = fs::read_to_string(root.backlight("acpi_video0").join("brightness")).unwrap();
+        assert_eq!(raw, "128");
+        assert!((controller.get_brightness().unwrap() - 128.0 / 255.0 * 100.0).abs() < 1e-9);
+        assert!(BacklightController::with_root(&root, "missing").is_err());
+        fs::remove_dir_all(root.path()).ok();
+    }
+
+    #[test]
+    fn test_battery_in_fake_tree() {
+        let root = fake_sysfs("charging", 100, "Charging");
+        assert!(!is_on_battery(&root));
+        fs::write(root.class("power_supply").join("BAT0").join("status"), "Discharging\n").unwrap();
+        assert!(is_on_battery(&root));
+        fs::remove_dir_all(root.path()).ok();
+
+        // No power_supply class at all: a desktop
+        assert!(!is_on_battery(&SysfsRoot::new("/nonexistent")));
+    }
+
+    #[test]
+    fn test_sysfs_root_resolution() {
+        assert!(SysfsRoot::system().is_system());
+        let configured = SysfsRoot::resolve(Some(Path::new("/tmp/fake-sys")));
+        assert_eq!(configured.path(), Path::new("/tmp/fake-sys"));
+        assert_eq!(configured.class("leds"), Path::new("/tmp/fake-sys/class/leds"));
+        assert!(!configured.is_system());
+    }
+
+    #[test]
+    fn test_drm_connectors_in_fake_tree() {
+        let root = fake_sysfs("drm", 255, "Charging");
+        let drm = root.class("drm");
+        let panel = drm.join("card0-eDP-1");
+        fs::create_dir_all(panel.join("acpi_video0")).unwrap();
+        fs::write(panel.join("status"), "connected\n").unwrap();
+        let monitor = drm.join("card0-DP-1");
+        fs::create_dir_all(monitor.join("i2c-5")).unwrap();
+        fs::write(monitor.join("status"), "disconnected\n").unwrap();
+        fs::create_dir_all(drm.join("card0")).unwrap();
+        fs::create_dir_all(drm.join("renderD128")).unwrap();
+
+        let connectors = root.drm_connectors();
+        assert_eq!(connectors, vec![
+            DrmConnector { name: "DP-1".to_string(), connected: false, ddc_bus: Some("i2c-5".to_string()), backlights: vec![] },
+            DrmConnector { name: "eDP-1".to_string(), connected: true, ddc_bus: None, backlights: vec!["acpi_video0".to_string()] },
+        ]);
+        fs::remove_dir_all(root.path()).ok();
+
+        assert!(SysfsRoot::new("/nonexistent").drm_connectors().is_empty());
+    }
+
+    #[test]
+    fn test_discovery_skips_held_displays() {
+        let root = fake_sysfs("held", 255, "Charging");
+        let mut config = Config::default().brightness;
+        config.method = "backlight".to_string();
+        config.gamma = GammaMode::Off;
+
+        let found = DisplayRegistry::discover(&config, &root);
+        assert_eq!(found.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>(), vec!["backlight:acpi_video0"]);
+        // A held display is not probed again, so its backend is never rebuilt behind its back
+        assert!(DisplayRegistry::discover_except(&config, &root, &["backlight:acpi_video0".to_string()]).is_empty());
+        fs::remove_dir_all(root.path()).ok();
+    }
+}
+
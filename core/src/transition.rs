@@ -0,0 +1,100 @@
+use std::time::Duration;
+use thiserror::Error;
+use crate::epilepsy::{BrightnessSpace, MAX_DELTA_PER_STEP, MIN_SAFE_INTERVAL_MS};
+
+// Discrete schedule for a transition on a device with few native levels
+// (max_brightness of 7, 15, 255, ...). Sampling the eased curve and rounding
+// would hit each level at uneven times, bunched where the curve is steep.
+// Instead every level in between gets an evenly spaced slot, and writes never
+// combine levels into a step larger than MAX_DELTA_PER_STEP. A device whose
+// single levels are already larger than that gets no plan: TransitionPlan::new
+// refuses the move with StepTooLarge and the caller decides what to write.
+#[derive(Debug, Clone)]
+pub struct TransitionPlan {
+    start: f64,
+    steps: Vec<f64>, // Percent of each level, the last one is the target's
+    spacing: Duration,
+    next: usize,
+    space: BrightnessSpace, // Where MAX_DELTA_PER_STEP is measured
+}
+
+// A move whose native levels are each a bigger perceived step than MAX_DELTA_PER_STEP
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[error("one native level is a step of {step:.1}, over the per-step limit of {MAX_DELTA_PER_STEP}")]
+pub struct StepTooLarge {
+    pub step: f64,         // Largest level of the move, in the guard's space
+    pub spacing: Duration, // Even share of the duration per level, at least MIN_SAFE_INTERVAL_MS
+}
+
+// Percent of the first native level from `from` towards `to`, never past `to`:
+// the last step lands on the target itself, which on an extended range is a
+// LUT level rather than the backlight's lowest one
+pub fn next_level(from: f64, to: f64, levels: u32) -> f64 {
+    let max = levels.saturating_sub(1).max(1) as f64;
+    let level = (from.clamp(0.0, 100.0) / 100.0 * max).round() + (to - from).signum();
+    let value = level.clamp(0.0, max) / max * 100.0;
+    if to < from { value.max(to) } else { value.min(to) }
+}
+
+impl TransitionPlan {
+    // None when there is nothing to spread: the move stays within one level, or
+    // the device is fine enough that every write already lands on a new level
+    pub fn new(from: f64, to: f64, duration: Duration, levels: u32, space: BrightnessSpace, interval: Duration) -> Result<Option<Self>, StepTooLarge> {
+        if levels < 2 {
+            return Ok(None);
+        }
+        let max = (levels - 1) as f64;
+        let level_of = |value: f64| (value.clamp(0.0, 100.0) / 100.0 * max).round() as i64;
+        let value_of = |level: i64| level as f64 / max * 100.0;
+
+        let (start, end) = (level_of(from), level_of(to));
+        let count = end.abs_diff(start) as u32;
+        let writes = duration.as_secs_f64() / interval.as_secs_f64().max(f64::EPSILON);
+        if count == 0 || count as f64 >= writes {
+            return Ok(None);
+        }
+
+        let direction = (end - start).signum();
+        let levels: Vec<i64> = (1..=count as i64).map(|i| start + i * direction).collect();
+        let step = levels.iter()
+            .map(|&l| (space.from_percent(value_of(l)) - space.from_percent(value_of(l - direction))).abs())
+            .fold(0.0, f64::max);
+        if step > MAX_DELTA_PER_STEP {
+            let spacing = (duration / count).max(interval).max(Duration::from_millis(MIN_SAFE_INTERVAL_MS as u64));
+            return Err(StepTooLarge { step, spacing });
+        }
+        let spacing = (duration / count).max(interval);
+        Ok(Some(Self { start: value_of(start), steps: levels.into_iter().map(value_of).collect(), spacing, next: 0, space }))
+    }
+
+    pub fn steps(&self) -> &[f64] {
+        &self.steps
+    }
+
+    pub fn spacing(&self) -> Duration {
+        self.spacing
+    }
+
+    // At least the requested duration, longer if the steps needed more room
+    pub fn duration(&self) -> Duration {
+        self.spacing * self.steps.len() as u32
+    }
+
+    // The value to write once `elapsed` reaches the next level's slot (the
+    // middle of its share of the duration). A late tick catches up over several
+    // levels only while the step stays within MAX_DELTA_PER_STEP.
+    pub fn due(&mut self, elapsed: Duration) -> Option<f64> {
+        let slots = (elapsed + self.spacing / 2).as_secs_f64() / self.spacing.as_secs_f64();
+        let due = (slots.floor() as usize).min(self.steps.len());
+        if due <= self.next {
+            return None;
+        }
+        let written = self.space.from_percent(self.next.checked_sub(1).map_or(self.start, |i| self.steps[i]));
+        let mut next = self.next + 1;
+        while next < due && (self.space.from_percent(self.steps[next]) - written).abs() <= MAX_DELTA_PER_STEP {
+            next += 1;
+        }
+        self.next = next;
+        Some(self.steps[next - 1])
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use crate::clock::{Clock, ManualClock};
+    use crate::epilepsy::{BrightnessSpace, EpilepsyGuard, MAX_DELTA_PER_STEP, MIN_SAFE_INTERVAL_MS};
+    use crate::gamma::ExtendedRangeController;
+    use crate::hardware::{BackendCapabilities, BrightnessController, DisplayRegistry, DummyController, HardwareError, ManagedDisplay, Privilege};
+    use crate::transition::{next_level, TransitionPlan};
+    use chrono::Utc;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    const TICK: Duration = Duration::from_millis(8);
+
+    // A sysfs panel with max_brightness 7 that records when each write lands
+    struct CoarsePanel {
+        clock: Arc<ManualClock>,
+        raw: u32,
+        writes: Arc<Mutex<Vec<(Instant, u32)>>>,
+    }
+
+    impl BrightnessController for CoarsePanel {
+        fn get_brightness(&self) -> Result<f64, HardwareError> {
+            Ok(self.raw as f64 / 7.0 * 100.0)
+        }
+
+        fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+            self.raw = (value / 100.0 * 7.0).round() as u32;
+            self.writes.lock().unwrap().push((self.clock.now(), self.raw));
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "coarse"
+        }
+
+        fn max_update_hz(&self) -> f64 {
+            125.0
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            BackendCapabilities { readback: true, resolution: 8, latency: Duration::from_millis(1), shows_osd: false, privilege: Privilege::VideoGroup }
+        }
+    }
+
+    #[test]
+    fn test_plan_refuses_coarse_levels() {
+        // 8 levels, 20% -> 80%: levels 2 to 6, each ~14% and so over the per-step limit
+        let refused = TransitionPlan::new(20.0, 80.0, Duration::from_millis(750), 8, BrightnessSpace::Linear, TICK).unwrap_err();
+        assert!((refused.step - 100.0 / 7.0).abs() < 1e-9);
+        assert_eq!(refused.spacing, Duration::from_millis(MIN_SAFE_INTERVAL_MS as u64));
+        // A longer move spreads the levels over its duration
+        let refused = TransitionPlan::new(20.0, 80.0, Duration::from_secs(5), 8, BrightnessSpace::Linear, TICK).unwrap_err();
+        assert_eq!(refused.spacing, Duration::from_secs(1));
+
+        // One level at a time, the last one on the target itself
+        assert!((next_level(20.0, 80.0, 8) - 200.0 / 7.0).abs() < 1e-9);
+        assert_eq!(next_level(500.0 / 7.0, 80.0, 8), 80.0);
+        assert_eq!(next_level(100.0 / 7.0, 5.0, 8), 5.0);
+    }
+
+    #[test]
+    fn test_plan_on_fine_levels() {
+        // 256 levels, a small move: evenly spread over the requested time, and a
+        // late tick catches up without exceeding the per-step limit
+        let mut plan = TransitionPlan::new(50.0, 55.0, Duration::from_millis(750), 256, BrightnessSpace::Linear, TICK).unwrap().unwrap();
+        assert_eq!(plan.steps().len(), 12);
+        assert_eq!(plan.duration(), plan.spacing() * 12);
+        assert!(plan.duration() <= Duration::from_millis(750));
+        let caught_up = plan.due(Duration::from_millis(750)).unwrap();
+        assert!(caught_up - 128.0 / 2.55 <= MAX_DELTA_PER_STEP);
+        assert!(caught_up < *plan.steps().last().unwrap());
+
+        // Enough native levels for every write, or nothing to cross: no plan
+        assert!(TransitionPlan::new(10.0, 90.0, Duration::from_millis(750), 256, BrightnessSpace::Linear, TICK).unwrap().is_none());
+        assert!(TransitionPlan::new(50.0, 50.2, Duration::from_millis(750), 256, BrightnessSpace::Linear, TICK).unwrap().is_none());
+    }
+
+    // Ramps a display from level 1 to level 6 of `panel`, wrapped by `wrap`
+    fn ramp_coarse(wrap: impl FnOnce(CoarsePanel) -> Box<dyn BrightnessController + Send>) -> Vec<(Instant, u32)> {
+        let clock = ManualClock::shared(Utc::now());
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let panel = CoarsePanel { clock: clock.clone(), raw: 1, writes: writes.clone() };
+        let mut guard = EpilepsyGuard::with_clock(100.0 / 7.0, clock.clone());
+        guard.set_space(BrightnessSpace::Linear);
+        let mut registry = DisplayRegistry::new();
+        registry.add(ManagedDisplay::new("backlight:coarse".into(), wrap(panel), guard));
+
+        let mut master = EpilepsyGuard::with_clock(100.0 / 7.0, clock.clone());
+        master.set_space(BrightnessSpace::Linear);
+        master.request_transition(600.0 / 7.0);
+        for _ in 0..500 {
+            registry.follow(&master);
+            master.tick_transition();
+            registry.tick();
+            clock.advance(TICK);
+        }
+        let writes = writes.lock().unwrap();
+        writes.clone()
+    }
+
+    // Every level from 2 to 6 exactly once, evenly spaced and never faster than 3Hz
+    fn assert_even_levels(writes: &[(Instant, u32)]) {
+        let mut levels: Vec<u32> = writes.iter().map(|(_, raw)| *raw).collect();
+        levels.dedup();
+        assert_eq!(levels, vec![2, 3, 4, 5, 6]);
+        let changes: Vec<Instant> = writes.windows(2).filter(|w| w[1].1 != w[0].1).map(|w| w[1].0).collect();
+        let gaps: Vec<Duration> = std::iter::once(writes[0].0).chain(changes).collect::<Vec<_>>()
+            .windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps.iter().all(|g| *g >= Duration::from_millis(MIN_SAFE_INTERVAL_MS as u64 - 8)), "{:?}", gaps);
+        assert!(gaps.iter().max().unwrap().as_millis() - gaps.iter().min().unwrap().as_millis() <= 16, "{:?}", gaps);
+    }
+
+    #[test]
+    fn test_coarse_display_steps_evenly() {
+        let writes = ramp_coarse(|panel| Box::new(panel));
+        // One write per level: a refused move never sends the eased values in between
+        assert_eq!(writes.len(), 5);
+        assert_even_levels(&writes);
+    }
+
+    #[test]
+    fn test_coarse_display_under_extended_range() {
+        // The LUT's levels below the floor do not make the backlight any finer
+        let writes = ramp_coarse(|panel| {
+            let extended = ExtendedRangeController::new(panel, DummyController::new(), 10.0);
+            assert_eq!(extended.capabilities().resolution, 8);
+            Box::new(extended)
+        });
+        assert_even_levels(&writes);
+    }
+}
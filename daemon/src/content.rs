@@ -1,8 +1,233 @@
+use anyhow::Result;
 use std::process::Command;
 use std::time::{Duration, Instant};
 use tracing::{warn, debug}; // info removed
 use std::fs;
 use std::io::Read;
+use core::clock::SharedClock;
+use core::epilepsy::{is_saturated_red, red_flash_value, relative_luminance, AreaFlashDetector, EpilepsyGuard, FlashDetector, FlashViolation, SafetyMode};
+
+// Flash analysis grid (cells are tracked independently, see AreaFlashDetector)
+pub const GRID_COLS: usize = 16;
+pub const GRID_ROWS: usize = 9;
+
+// Flash detection needs well over two samples per flash: 20Hz sees up to 10
+// flashes/s, the WCAG limit is 3
+pub const FLASH_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+// Slower than this and a hazardous flash rate can no longer be told apart
+pub const MAX_FLASH_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+// How long the screen stays dimmed after a flash hazard is reported
+pub const FLASH_HAZARD_HOLD: Duration = Duration::from_secs(10);
+pub const FLASH_HAZARD_MULTIPLIER: f64 = 0.2;
+// Never dim below the guard's own floor, the screen must stay readable
+pub const FLASH_HAZARD_FLOOR: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CellStats {
+    pub relative_luminance: f64,
+    pub red_value: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    pub luma: f64,              // Rec. 601 luma, drives the flashbang curve
+    pub red_fraction: f64,      // Share of sampled pixels that are saturated red
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<CellStats>,  // GRID_COLS x GRID_ROWS, row-major; drives flash detection
+}
+
+impl FrameStats {
+    // Saturated red reads dark in luma but is just as harsh as white, so weigh it in
+    pub fn flashbang_intensity(&self) -> f64 {
+        self.luma.max(self.red_fraction)
+    }
+
+    pub fn cell_size_px(&self) -> (f64, f64) {
+        (self.width as f64 / GRID_COLS as f64, self.height as f64 / GRID_ROWS as f64)
+    }
+}
+
+// Sums sampled pixels into FrameStats, whichever way the screen was captured
+pub struct FrameAccumulator {
+    width: usize,
+    height: usize,
+    total_luma: f64,
+    red_count: usize,
+    count: usize,
+    cells: Vec<CellStats>,
+    cell_counts: Vec<usize>,
+}
+
+impl FrameAccumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            total_luma: 0.0,
+            red_count: 0,
+            count: 0,
+            cells: vec![CellStats::default(); GRID_COLS * GRID_ROWS],
+            cell_counts: vec![0; GRID_COLS * GRID_ROWS],
+        }
+    }
+
+    // One pixel at (x, y) with sRGB components normalised to 0.0 - 1.0
+    pub fn add(&mut self, x: usize, y: usize, r: f64, g: f64, b: f64) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        // Rec. 601 luma
+        self.total_luma += 0.299 * r + 0.587 * g + 0.114 * b;
+        if is_saturated_red(r, g, b) {
+            self.red_count += 1;
+        }
+        self.count += 1;
+
+        let col = (x * GRID_COLS / self.width).min(GRID_COLS - 1);
+        let row = (y * GRID_ROWS / self.height).min(GRID_ROWS - 1);
+        let cell = row * GRID_COLS + col;
+        self.cells[cell].relative_luminance += relative_luminance(r, g, b);
+        self.cells[cell].red_value += red_flash_value(r, g, b);
+        self.cell_counts[cell] += 1;
+    }
+
+    pub fn finish(mut self) -> Option<FrameStats> {
+        if self.count == 0 {
+            return None;
+        }
+        for (cell, &n) in self.cells.iter_mut().zip(&self.cell_counts) {
+            if n > 0 {
+                cell.relative_luminance /= n as f64;
+                cell.red_value /= n as f64;
+            }
+        }
+        Some(FrameStats {
+            luma: self.total_luma / self.count as f64,
+            red_fraction: self.red_count as f64 / self.count as f64,
+            width: self.width,
+            height: self.height,
+            cells: self.cells,
+        })
+    }
+}
+
+// A way of grabbing the screen. Flash detection needs one that keeps up with
+// FLASH_SAMPLE_INTERVAL; spectacle only feeds the flashbang luma.
+pub trait FrameSource: Send {
+    fn name(&self) -> &str;
+    fn capture(&mut self) -> Result<FrameStats>;
+}
+
+// Runs the general and red detectors over every cell of a frame
+pub struct FlashMonitor {
+    field_px: (f64, f64),
+    detectors: Option<(usize, usize, AreaFlashDetector, AreaFlashDetector)>,
+}
+
+impl FlashMonitor {
+    pub fn new(field_px: (f64, f64)) -> Self {
+        Self { field_px, detectors: None }
+    }
+
+    pub fn push(&mut self, t: Duration, frame: &FrameStats) -> Option<FlashViolation> {
+        // Cell size depends on the capture size, rebuild on resolution change
+        if !matches!(self.detectors, Some((w, h, _, _)) if w == frame.width && h == frame.height) {
+            let cell_size = frame.cell_size_px();
+            self.detectors = Some((
+                frame.width,
+                frame.height,
+                AreaFlashDetector::new(FlashDetector::new(), GRID_COLS, GRID_ROWS, cell_size, self.field_px),
+                AreaFlashDetector::new(FlashDetector::red(), GRID_COLS, GRID_ROWS, cell_size, self.field_px),
+            ));
+        }
+
+        let (_, _, general_detector, red_detector) = self.detectors.as_mut()?;
+        let luminance: Vec<f64> = frame.cells.iter().map(|c| c.relative_luminance).collect();
+        let red_values: Vec<f64> = frame.cells.iter().map(|c| c.red_value).collect();
+        let general = general_detector.push(t, &luminance);
+        let red = red_detector.push(t, &red_values);
+        general.or(red)
+    }
+}
+
+// Grabs frames from a fast source and feeds them to the flash detectors,
+// timestamped by the shared clock
+pub struct FlashSampler {
+    source: Box<dyn FrameSource>,
+    monitor: FlashMonitor,
+    clock: SharedClock,
+    start: Instant,
+}
+
+impl FlashSampler {
+    pub fn new(source: Box<dyn FrameSource>, field_px: (f64, f64), clock: SharedClock) -> Self {
+        let start = clock.now();
+        Self { source, monitor: FlashMonitor::new(field_px), clock, start }
+    }
+
+    pub fn source_name(&self) -> &str {
+        self.source.name()
+    }
+
+    pub fn sample(&mut self) -> Result<Option<FlashViolation>> {
+        let frame = self.source.capture()?;
+        let t = self.clock.now().duration_since(self.start);
+        Ok(self.monitor.push(t, &frame))
+    }
+}
+
+// What the main loop does about reported flash hazards: dim hard at once and
+// hold the dim, and on a repeat while still holding stop once the dim has played out
+#[derive(Debug, Default)]
+pub struct FlashHazard {
+    hold_until: Option<Instant>,
+    pending_stop: bool,
+}
+
+impl FlashHazard {
+    pub fn report(&mut self, guard: &mut EpilepsyGuard) {
+        let now = guard.clock().now();
+        if guard.mode != SafetyMode::EmergencyStop {
+            if !self.holding(now) {
+                // Directly, not through the autopilot: that only runs once a second
+                // and not at all while locked or after a manual change
+                let dim = (guard.current_brightness * FLASH_HAZARD_MULTIPLIER).max(FLASH_HAZARD_FLOOR);
+                if dim < guard.current_brightness {
+                    guard.force_instant_transition(dim);
+                }
+            } else {
+                // Escalate once: stopping again would make a timed freeze indefinite
+                self.pending_stop = true;
+            }
+        }
+        self.hold_until = Some(now + FLASH_HAZARD_HOLD);
+    }
+
+    fn holding(&self, now: Instant) -> bool {
+        self.hold_until.is_some_and(|until| now < until)
+    }
+
+    // Cap on the content multiplier while the hold lasts, keeps the dim in place
+    pub fn multiplier_cap(&mut self, now: Instant) -> Option<f64> {
+        if self.holding(now) {
+            return Some(FLASH_HAZARD_MULTIPLIER);
+        }
+        self.hold_until = None;
+        None
+    }
+
+    // Runs before the guard ticks; true when this call stopped it
+    pub fn tick(&mut self, guard: &mut EpilepsyGuard) -> bool {
+        if self.pending_stop && guard.transition.is_none() {
+            self.pending_stop = false;
+            guard.emergency_stop(None);
+            return true;
+        }
+        false
+    }
+}
 
 pub struct ContentAnalyzer {
     last_check: Instant,
@@ -15,7 +240,8 @@ impl ContentAnalyzer {
         }
     }
 
-    pub fn get_screen_brightness(&mut self) -> Option<f64> {
+    // Flashbang luma only: too slow for flash detection, see FlashSampler
+    pub fn analyze_screen(&mut self) -> Option<FrameStats> {
         // Limit polling to 1Hz (1000ms) because spectacle is slow (~400ms)
         if self.last_check.elapsed() < Duration::from_millis(1000) {
             return None;
@@ -113,14 +339,14 @@ impl ContentAnalyzer {
                 pos = 2;
 
                 // Read Width
-                let (_width, next_pos) = match read_number(&data, pos) {
+                let (width, next_pos) = match read_number(&data, pos) {
                     Some(v) => v,
                     None => { debug!("Failed to parse width"); return None; }
                 };
                 pos = next_pos;
 
                 // Read Height
-                let (_height, next_pos) = match read_number(&data, pos) {
+                let (height, next_pos) = match read_number(&data, pos) {
                      Some(v) => v,
                      None => { debug!("Failed to parse height"); return None; }
                 };
@@ -144,34 +370,27 @@ impl ContentAnalyzer {
                 }
 
                 let pixels = &data[pos..];
+                if width == 0 || height == 0 {
+                    debug!("Invalid frame size {}x{}", width, height);
+                    return None;
+                }
+                let mut frame = FrameAccumulator::new(width, height);
                 // Stride 50 is fine for 1080p
-                let stride = 50; 
-                let mut total_luma = 0.0;
-                let mut count = 0;
+                let stride = 50;
 
                 // RGB is 3 bytes
                 for i in (0..pixels.len()).step_by(3 * stride) {
                     if i + 2 >= pixels.len() { break; }
-                    let r = pixels[i] as f64;
-                    let g = pixels[i+1] as f64;
-                    let b = pixels[i+2] as f64;
-                    
                     // Normalize to 0-1 based on maxval
-                    let r_n = r / maxval as f64;
-                    let g_n = g / maxval as f64;
-                    let b_n = b / maxval as f64;
-                    
-                    // Rec. 601 luma
-                    let luma = 0.299 * r_n + 0.587 * g_n + 0.114 * b_n;
-                    total_luma += luma;
-                    count += 1;
+                    let r = pixels[i] as f64 / maxval as f64;
+                    let g = pixels[i+1] as f64 / maxval as f64;
+                    let b = pixels[i+2] as f64 / maxval as f64;
+
+                    let pixel = i / 3;
+                    frame.add(pixel % width, pixel / width, r, g, b);
                 }
-                
-                if count == 0 { return None; }
-                
-                let avg_luma = total_luma / count as f64;
-                
-                Some(avg_luma)
+
+                frame.finish()
             },
             Err(e) => {
                 warn!("Failed to execute spectacle: {}", e);
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use crate::content::{FlashHazard, FlashSampler, FrameAccumulator, FrameSource, FrameStats, FLASH_HAZARD_FLOOR, FLASH_HAZARD_MULTIPLIER, FLASH_SAMPLE_INTERVAL};
+    use anyhow::{anyhow, Result};
+    use chrono::Utc;
+    use core::clock::{Clock, ManualClock};
+    use core::epilepsy::{visual_field_px, EpilepsyGuard, FlashKind, FlashViolation, SafetyMode};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    type Rgb = (f64, f64, f64);
+
+    const RED: Rgb = (1.0, 0.0, 0.0);
+    // Same relative luminance as RED: only the red detector sees the change
+    const GREY: Rgb = (0.5, 0.5, 0.5);
+
+    // The whole screen switching between two colours `hz` times a second
+    struct Strobe {
+        clock: Arc<ManualClock>,
+        hz: f64,
+        low: Rgb,
+        high: Rgb,
+    }
+
+    impl FrameSource for Strobe {
+        fn name(&self) -> &str {
+            "strobe"
+        }
+
+        fn capture(&mut self) -> Result<FrameStats> {
+            let half_periods = (self.clock.elapsed().as_secs_f64() * self.hz * 2.0) as u64;
+            let (r, g, b) = if half_periods.is_multiple_of(2) { self.low } else { self.high };
+            let mut frame = FrameAccumulator::new(1920, 1080);
+            for y in (0..1080).step_by(40) {
+                for x in (0..1920).step_by(40) {
+                    frame.add(x, y, r, g, b);
+                }
+            }
+            frame.finish().ok_or_else(|| anyhow!("empty frame"))
+        }
+    }
+
+    fn sampler(clock: &Arc<ManualClock>, hz: f64, low: Rgb, high: Rgb) -> FlashSampler {
+        let source = Strobe { clock: clock.clone(), hz, low, high };
+        FlashSampler::new(Box::new(source), visual_field_px(60.0, 96.0), clock.clone())
+    }
+
+    // Samples at the daemon's rate for `secs`, collecting every violation
+    fn sample_for(hz: f64, low: Rgb, high: Rgb, secs: u64) -> Vec<FlashViolation> {
+        let clock = ManualClock::shared(Utc::now());
+        let mut sampler = sampler(&clock, hz, low, high);
+        let mut violations = Vec::new();
+        while clock.elapsed() < Duration::from_secs(secs) {
+            violations.extend(sampler.sample().unwrap());
+            clock.advance(FLASH_SAMPLE_INTERVAL);
+        }
+        violations
+    }
+
+    #[test]
+    fn test_5hz_flash_detected() {
+        let violations = sample_for(5.0, (0.1, 0.1, 0.1), (0.9, 0.9, 0.9), 2);
+        assert!(!violations.is_empty());
+        assert!(violations.iter().all(|v| v.kind == FlashKind::General));
+        assert!(violations.iter().any(|v| v.flashes >= 4.5), "{:?}", violations);
+    }
+
+    #[test]
+    fn test_2hz_flash_allowed() {
+        assert!(sample_for(2.0, (0.1, 0.1, 0.1), (0.9, 0.9, 0.9), 3).is_empty());
+        assert!(sample_for(2.0, GREY, RED, 3).is_empty());
+    }
+
+    #[test]
+    fn test_red_flash_escalates_to_emergency_stop() {
+        // Capture -> red detector -> hazard hold -> hard dim -> stop, the way the
+        // main loop runs them
+        let clock = ManualClock::shared(Utc::now());
+        let mut sampler = sampler(&clock, 5.0, GREY, RED);
+        let mut guard = EpilepsyGuard::with_clock(80.0, clock.clone());
+        let mut hazard = FlashHazard::default();
+        let mut kinds = Vec::new();
+        let mut stopped_at = None;
+
+        while clock.elapsed() < Duration::from_secs(3) && stopped_at.is_none() {
+            if let Some(violation) = sampler.sample().unwrap() {
+                kinds.push(violation.kind);
+                hazard.report(&mut guard);
+            }
+            assert_eq!(hazard.multiplier_cap(clock.now()).is_some(), !kinds.is_empty());
+            if hazard.tick(&mut guard) {
+                stopped_at = Some(clock.elapsed());
+            }
+            guard.tick_transition();
+            clock.advance(FLASH_SAMPLE_INTERVAL);
+        }
+
+        assert!(!kinds.is_empty() && kinds.iter().all(|k| *k == FlashKind::Red), "{:?}", kinds);
+        assert!(stopped_at.is_some_and(|t| t < Duration::from_secs(2)), "{:?}", stopped_at);
+        assert_eq!(guard.mode, SafetyMode::EmergencyStop);
+        assert!((guard.current_brightness - 80.0 * FLASH_HAZARD_MULTIPLIER).abs() < 0.1);
+
+        // Still flashing while stopped: no further dimming, the freeze stays as it is
+        for _ in 0..20 {
+            if sampler.sample().unwrap().is_some() {
+                hazard.report(&mut guard);
+            }
+            assert!(!hazard.tick(&mut guard));
+            guard.tick_transition();
+            clock.advance(FLASH_SAMPLE_INTERVAL);
+        }
+        assert!((guard.current_brightness - 80.0 * FLASH_HAZARD_MULTIPLIER).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_first_flash_hazard_dims_at_once() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(80.0, clock.clone());
+        // The autopilot would not run: a manual value is held
+        guard.is_locked = true;
+        let mut hazard = FlashHazard::default();
+
+        hazard.report(&mut guard);
+        for _ in 0..10 {
+            assert!(!hazard.tick(&mut guard));
+            guard.tick_transition();
+            clock.advance(FLASH_SAMPLE_INTERVAL);
+        }
+        assert!((guard.current_brightness - 80.0 * FLASH_HAZARD_MULTIPLIER).abs() < 0.1);
+        assert_ne!(guard.mode, SafetyMode::EmergencyStop);
+
+        // A dim screen only goes down to the floor
+        let mut guard = EpilepsyGuard::with_clock(10.0, clock.clone());
+        FlashHazard::default().report(&mut guard);
+        for _ in 0..10 {
+            guard.tick_transition();
+            clock.advance(FLASH_SAMPLE_INTERVAL);
+        }
+        assert!((guard.current_brightness - FLASH_HAZARD_FLOOR).abs() < 0.1);
+    }
+}
+
@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Context, Result};
+use core::epilepsy::EpilepsyGuard;
+use core::hotkey::{ChordTracker, Hotkey};
+use evdev::{Device, EventType, Key};
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+use zbus::{Connection, Proxy};
+
+const PORTAL_DEST: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SHORTCUTS_IFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const SHORTCUT_ID: &str = "emergency-stop";
+
+// Sends () on every press of the chord. Prefers the XDG GlobalShortcuts portal
+// (works on Wayland without extra permissions) and falls back to reading evdev
+// keyboards directly, which needs the user in the `input` group.
+pub async fn listen(hotkey: Hotkey, tx: mpsc::UnboundedSender<()>) {
+    match portal_listen(&hotkey, &tx).await {
+        Ok(()) => warn!("GlobalShortcuts portal session ended"),
+        Err(e) => info!("GlobalShortcuts portal unavailable ({}), falling back to evdev", e),
+    }
+    if let Err(e) = evdev_listen(hotkey, tx).await {
+        warn!("⚠️ Emergency hotkey disabled: {}", e);
+    }
+}
+
+// Every press dims to `dim_level` and holds an untimed stop, also when already
+// stopped: a timed freeze or a stop left at full brightness gets dimmed too
+pub fn on_press(guard: &mut EpilepsyGuard, dim_level: f64) {
+    warn!("EMERGENCY STOP ACTIVATED (hotkey, was {:?} at {:.1}%)", guard.mode, guard.current_brightness);
+    guard.emergency_stop_dimmed(dim_level, None);
+}
+
+async fn portal_listen(hotkey: &Hotkey, tx: &mpsc::UnboundedSender<()>) -> Result<()> {
+    let conn = Connection::session().await?;
+    let shortcuts = Proxy::new(&conn, PORTAL_DEST, PORTAL_PATH, SHORTCUTS_IFACE).await?;
+
+    let options: HashMap<&str, Value> = HashMap::from([
+        ("handle_token", Value::from("epilyzer_session")),
+        ("session_handle_token", Value::from("epilyzer")),
+    ]);
+    let results = portal_request(&conn, "epilyzer_session", || async {
+        let handle: OwnedObjectPath = shortcuts.call("CreateSession", &(options,)).await?;
+        Ok(handle)
+    }).await?;
+    let session: String = results
+        .get("session_handle")
+        .and_then(|v| String::try_from(v.try_clone().ok()?).ok())
+        .ok_or_else(|| anyhow!("CreateSession returned no session handle"))?;
+    let session = OwnedObjectPath::try_from(session)?;
+
+    let description = format!("Emergency stop ({})", hotkey);
+    let shortcut: HashMap<&str, Value> = HashMap::from([
+        ("description", Value::from(description.as_str())),
+        ("preferred_trigger", Value::from(hotkey.portal_trigger())),
+    ]);
+    let bind_options: HashMap<&str, Value> = HashMap::from([("handle_token", Value::from("epilyzer_bind"))]);
+    let bound = portal_request(&conn, "epilyzer_bind", || async {
+        let handle: OwnedObjectPath = shortcuts
+            .call("BindShortcuts", &(&session, vec![(SHORTCUT_ID, shortcut)], "", bind_options))
+            .await?;
+        Ok(handle)
+    }).await?;
+    // A declined confirmation (KDE) or a portal without a trigger still answers
+    // with success; without a trigger nothing would ever be activated
+    let trigger = bound_trigger(&bound).ok_or_else(|| anyhow!("portal assigned no trigger to the shortcut"))?;
+    info!("🔑 Emergency hotkey {} bound via GlobalShortcuts portal as {}", hotkey, trigger);
+
+    let mut activated = shortcuts.receive_signal("Activated").await?;
+    while let Some(msg) = activated.next().await {
+        let (handle, id, _timestamp, _options): (OwnedObjectPath, String, u64, HashMap<String, OwnedValue>) =
+            msg.body().deserialize()?;
+        if handle == session && id == SHORTCUT_ID && tx.send(()).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// trigger_description of our shortcut in the BindShortcuts results, if it got one
+pub fn bound_trigger(results: &HashMap<String, OwnedValue>) -> Option<String> {
+    let shortcuts = results.get("shortcuts")?.try_clone().ok()?;
+    let shortcuts = Vec::<(String, HashMap<String, OwnedValue>)>::try_from(shortcuts).ok()?;
+    let (_, properties) = shortcuts.into_iter().find(|(id, _)| id == SHORTCUT_ID)?;
+    let trigger = String::try_from(properties.get("trigger_description")?.try_clone().ok()?).ok()?;
+    (!trigger.trim().is_empty()).then_some(trigger)
+}
+
+// Portal calls answer through a Request object; subscribe to its Response
+// signal before making the call so the reply cannot be missed.
+async fn portal_request<F, Fut>(conn: &Connection, token: &str, call: F) -> Result<HashMap<String, OwnedValue>>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<OwnedObjectPath>>,
+{
+    let sender = conn
+        .unique_name()
+        .ok_or_else(|| anyhow!("no unique bus name"))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+    let request = Proxy::new(conn, PORTAL_DEST, path, "org.freedesktop.portal.Request").await?;
+    let mut responses = request.receive_signal("Response").await?;
+
+    call().await?;
+
+    let msg = responses.next().await.ok_or_else(|| anyhow!("portal request dropped"))?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) = msg.body().deserialize()?;
+    if code != 0 {
+        return Err(anyhow!("portal request denied (response {})", code));
+    }
+    Ok(results)
+}
+
+async fn evdev_listen(hotkey: Hotkey, tx: mpsc::UnboundedSender<()>) -> Result<()> {
+    let wanted = Key::new(hotkey.key_code());
+    let keyboards: Vec<(std::path::PathBuf, Device)> = evdev::enumerate()
+        .filter(|(_, d)| d.supported_keys().is_some_and(|keys| keys.contains(wanted)))
+        .collect();
+    if keyboards.is_empty() {
+        return Err(anyhow!("no readable keyboards in /dev/input (is the user in the 'input' group?)"));
+    }
+
+    // One reader thread per device, a single tracker so modifiers on one
+    // keyboard combine with keys on another
+    let (key_tx, mut key_rx) = mpsc::unbounded_channel::<(u16, i32)>();
+    for (path, mut device) in keyboards {
+        info!("🔑 Watching {} for emergency hotkey {}", path.display(), hotkey);
+        let key_tx = key_tx.clone();
+        std::thread::spawn(move || loop {
+            match device.fetch_events() {
+                Ok(events) => {
+                    for ev in events.filter(|ev| ev.event_type() == EventType::KEY) {
+                        if key_tx.send((ev.code(), ev.value())).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Lost keyboard {}: {}", path.display(), e);
+                    return;
+                }
+            }
+        });
+    }
+    drop(key_tx);
+
+    let mut tracker = ChordTracker::new(hotkey);
+    while let Some((code, value)) = key_rx.recv().await {
+        if tracker.feed(code, value) {
+            tx.send(()).context("daemon stopped listening for the hotkey")?;
+        }
+    }
+    Err(anyhow!("all keyboards disconnected"))
+}
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use crate::hotkey::{bound_trigger, on_press};
+    use chrono::Utc;
+    use core::clock::ManualClock;
+    use core::epilepsy::{EpilepsyGuard, SafetyMode};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use zbus::zvariant::{OwnedValue, Value};
+
+    // BindShortcuts results as the portal sends them: a(sa{sv}) under "shortcuts"
+    fn results(trigger: Option<&str>) -> HashMap<String, OwnedValue> {
+        let mut properties: HashMap<String, Value> = HashMap::from([("description".to_string(), Value::from("Emergency stop"))]);
+        if let Some(trigger) = trigger {
+            properties.insert("trigger_description".to_string(), Value::from(trigger));
+        }
+        let shortcuts = Value::from(vec![("emergency-stop".to_string(), properties)]);
+        HashMap::from([("shortcuts".to_string(), OwnedValue::try_from(shortcuts).unwrap())])
+    }
+
+    #[test]
+    fn test_bind_needs_a_trigger() {
+        assert_eq!(bound_trigger(&results(Some("Ctrl+Alt+Escape"))).as_deref(), Some("Ctrl+Alt+Escape"));
+        // Declined or unassigned: fall back to evdev
+        assert_eq!(bound_trigger(&results(Some(""))), None);
+        assert_eq!(bound_trigger(&results(None)), None);
+        assert_eq!(bound_trigger(&HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_press_dims_an_existing_stop() {
+        let clock = ManualClock::shared(Utc::now());
+        let mut guard = EpilepsyGuard::with_clock(80.0, clock.clone());
+        // Frozen bright, e.g. from the GUI with a timeout
+        guard.emergency_stop(Some(Duration::from_secs(30)));
+
+        on_press(&mut guard, 10.0);
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(guard.tick_transition(), Some(10.0));
+        assert_eq!(guard.mode, SafetyMode::EmergencyStop);
+        // Untimed now: still stopped long after the old freeze would have ended
+        assert_eq!(guard.freeze_remaining(), None);
+        clock.advance(Duration::from_secs(60));
+        guard.tick_transition();
+        assert_eq!(guard.mode, SafetyMode::EmergencyStop);
+
+        // Pressed again while stopped and dim: stays stopped where it is
+        on_press(&mut guard, 10.0);
+        assert_eq!(guard.tick_transition(), None);
+        assert_eq!(guard.current_brightness, 10.0);
+    }
+}
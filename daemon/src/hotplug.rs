@@ -0,0 +1,144 @@
+use anyhow::Result;
+use core::config::BrightnessConfig;
+use core::gamma;
+use core::hardware::{BoxedController, DisplayRegistry};
+use core::hotplug::{parse_uevent, Uevent};
+use core::sysfs::SysfsRoot;
+use std::collections::HashSet;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+// Multicast groups of NETLINK_KOBJECT_UEVENT: raw kernel events, and the same
+// events re-sent by udev once its rules (permissions, symlinks) have run
+const KERNEL_GROUP: u32 = 1;
+const UDEV_GROUP: u32 = 2;
+
+// Docking or a GPU switch fires a burst of events; probe once it has settled
+const SETTLE: Duration = Duration::from_secs(2);
+
+// Result of one re-probe: the displays discovery reaches that are not held yet
+// or whose device node was re-created (touched, their controller must be
+// rebuilt), and the held ones whose device went away
+pub struct HotplugScan {
+    pub touched: HashSet<String>,
+    pub removed: HashSet<String>,
+    pub found: Vec<(String, BoxedController)>,
+}
+
+// `held` lists the displays the daemon manages, kept current by the main loop
+pub async fn watch(config: BrightnessConfig, sysfs: SysfsRoot, held: Arc<Mutex<Vec<String>>>, tx: mpsc::UnboundedSender<HotplugScan>) {
+    let socket = match open_socket() {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("⚠️ Display hotplug disabled: {}", e);
+            return;
+        }
+    };
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<Uevent>();
+    std::thread::spawn(move || read_events(socket, event_tx));
+
+    while let Some(first) = event_rx.recv().await {
+        let mut events = vec![first];
+        while let Ok(Some(e)) = tokio::time::timeout(SETTLE, event_rx.recv()).await {
+            events.push(e);
+        }
+        for e in &events {
+            info!("Hotplug: {} {} ({})", e.action, e.sysname(), e.subsystem);
+        }
+
+        // Anyone can multicast on the udev group; a forged event only costs a re-probe
+        let touched: HashSet<String> = events.iter()
+            .filter(|e| e.action == "add" || e.action == "remove")
+            .filter_map(Uevent::display_id)
+            .collect();
+        let mut removed: HashSet<String> = events.iter()
+            .filter(|e| e.action == "remove")
+            .filter_map(Uevent::display_id)
+            .collect();
+        let drm_changed = events.iter().any(|e| e.subsystem == "drm");
+        // Touched displays are probed again and rebuilt, every other held one is kept as is
+        let skip: Vec<String> = held.lock().unwrap().iter().filter(|id| !touched.contains(*id)).cloned().collect();
+        let (probe_config, probe_sysfs) = (config.clone(), sysfs.clone());
+        let probe = tokio::task::spawn_blocking(move || {
+            let found = DisplayRegistry::discover_except(&probe_config, &probe_sysfs, &skip);
+            (found, if drm_changed { unplugged_gamma_outputs(&skip) } else { Vec::new() })
+        });
+        let found = match probe.await {
+            Ok((found, unplugged)) => {
+                removed.extend(unplugged);
+                found
+            }
+            Err(e) => {
+                warn!("Hotplug re-probe failed: {}", e);
+                continue;
+            }
+        };
+        if tx.send(HotplugScan { touched, removed, found }).is_err() {
+            return;
+        }
+    }
+}
+
+// drm connector events do not name the output; a held gamma display is gone
+// once the display server no longer lists its output
+fn unplugged_gamma_outputs(held: &[String]) -> Vec<String> {
+    let Ok(outputs) = gamma::output_names() else { return Vec::new() };
+    held.iter()
+        .filter(|id| id.strip_prefix("gamma:").is_some_and(|output| {
+            !outputs.iter().any(|o| gamma::output_key(o) == gamma::output_key(output))
+        }))
+        .cloned()
+        .collect()
+}
+
+fn open_socket() -> Result<OwnedFd> {
+    // SAFETY: plain socket(2), ownership moves into OwnedFd right away
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_KOBJECT_UEVENT) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // Without udevd nothing is re-broadcast, so listen to the kernel directly
+    let group = if std::path::Path::new("/run/udev/control").exists() { UDEV_GROUP } else { KERNEL_GROUP };
+    // SAFETY: sockaddr_nl is plain data, all-zero is a valid value
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = group;
+    let ret = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    info!("🔌 Watching backlight, drm and i2c-dev uevents for display hotplug");
+    Ok(socket)
+}
+
+fn read_events(socket: OwnedFd, tx: mpsc::UnboundedSender<Uevent>) {
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        // SAFETY: buf outlives the call and its length is passed along
+        let n = unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        if n < 0 {
+            let err = std::io::Error::last_os_error();
+            // ENOBUFS: a burst overflowed the socket, the next event still triggers a probe
+            if err.kind() == std::io::ErrorKind::Interrupted || err.raw_os_error() == Some(libc::ENOBUFS) {
+                continue;
+            }
+            warn!("Hotplug socket failed: {}", err);
+            return;
+        }
+        let Some(event) = parse_uevent(&buf[..n as usize]) else { continue };
+        if event.is_display_event() && tx.send(event).is_err() {
+            return;
+        }
+    }
+}
@@ -22,7 +22,11 @@ impl DataLogger {
             .unwrap_or(PathBuf::from("/tmp"))
             .join("auto_brightness_history.csv");
             
-        Self { file_path: path }
+        Self::at(path)
+    }
+
+    pub fn at(file_path: PathBuf) -> Self {
+        Self { file_path }
     }
 
     pub fn log(&self, event_type: &str, brightness: f64, mode: &str) -> Result<()> {
@@ -1,23 +1,43 @@
 use anyhow::{Context, Result};
 use clap::Parser;
+use core::ambient::{AmbientLightSource, LuxResponse};
+use core::clock::{system_clock, SharedClock};
 use core::config::Config;
-use core::epilepsy::EpilepsyGuard;
-use core::hardware::{BrightnessController, BacklightController, DdcUtilController, DummyController};
+use core::epilepsy::{visual_field_px, EpilepsyGuard, SafetyMode};
+use core::failover::BackendStatus;
+use core::keyboard::{LedController, ManagedKeyboard};
+use core::hardware::{BacklightController, BoxedController, BrightnessController, DisplayRegistry, DummyController, ManagedColorTemperature, ManagedDisplay, SafetyAuditor, SafetyEventLog};
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tracing::{info, error, warn, Level};
 use tracing_subscriber::FmtSubscriber;
-use std::fs;
 use std::process::Command;
 
 mod logging;
 // mod ml; // Removed as unused
 mod state;
+mod capture;
 mod content;
-
+mod hotkey;
+mod hotplug;
+mod main_loop;
+
+#[cfg(test)]
+mod content_tests;
+#[cfg(test)]
+mod hotkey_tests;
+#[cfg(test)]
+mod main_loop_tests;
+
+use crate::content::{FlashSampler, FLASH_SAMPLE_INTERVAL, MAX_FLASH_SAMPLE_INTERVAL};
+use crate::hotplug::HotplugScan;
+use crate::main_loop::MainLoop;
 use crate::state::StateManager;
 
+const AMBIENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -28,22 +48,57 @@ struct Args {
     dry_run: bool,
 }
 
-fn is_on_battery() -> bool {
-    if let Ok(entries) = fs::read_dir("/sys/class/power_supply") {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            if name.starts_with("BAT") {
-                let status_path = path.join("status");
-                if let Ok(status) = fs::read_to_string(status_path) {
-                    if status.trim() == "Discharging" {
-                        return true;
-                    }
-                }
+
+// Every hardware write, whatever its source, goes through the display's auditor
+fn wrap_backend(backend: BoxedController, config: &Config, clock: &SharedClock, events: &SafetyEventLog) -> BoxedController {
+    let mut controller = SafetyAuditor::with_clock(backend, config.epilepsy_protection.auditor_min_delta, clock.clone());
+    controller.set_event_log(events.clone());
+    Box::new(controller)
+}
+
+fn managed_display(id: String, backend: BoxedController, config: &Config, clock: &SharedClock, events: &SafetyEventLog, initial: f64) -> Option<ManagedDisplay> {
+    let display_config = config.display(&id);
+    if display_config.is_some_and(|d| !d.enabled) {
+        info!("Display '{}' disabled in config", id);
+        return None;
+    }
+    let mut display_guard = EpilepsyGuard::with_clock(initial, clock.clone());
+    display_guard.set_space(config.brightness.space);
+    let mut display = ManagedDisplay::new(id, wrap_backend(backend, config, clock, events), display_guard);
+    if let Some(dc) = display_config {
+        display.offset = dc.offset;
+        display.scale = dc.scale;
+    }
+    Some(display)
+}
+
+// Drops displays whose device was removed, rebuilds the ones whose device
+// node was re-created and ramps new ones in from a safe level. Held displays
+// the probe skipped are left untouched.
+fn apply_hotplug(displays: &mut DisplayRegistry, scan: HotplugScan, master: &EpilepsyGuard, config: &Config, clock: &SharedClock, events: &SafetyEventLog) {
+    let found: HashSet<String> = scan.found.iter().map(|(id, _)| id.clone()).collect();
+    let gone: Vec<String> = displays.displays().iter()
+        .map(|d| d.id.clone())
+        // The fallback dummy stays until a real display replaces it
+        .filter(|id| if id == "dummy" { !found.is_empty() } else { scan.removed.contains(id) && !found.contains(id) })
+        .collect();
+    for id in gone {
+        displays.remove(&id);
+    }
+
+    for (id, backend) in scan.found {
+        if displays.contains(&id) {
+            if scan.touched.contains(&id) {
+                displays.replace_controller(&id, wrap_backend(backend, config, clock, events));
+            }
+            continue;
+        }
+        if let Some(display) = managed_display(id.clone(), backend, config, clock, events, master.current_brightness) {
+            if let Err(e) = displays.attach(display, master) {
+                error!("Failed to set start brightness on '{}': {}", id, e);
             }
         }
     }
-    false
 }
 
 #[tokio::main]
@@ -64,99 +119,109 @@ async fn main() -> Result<()> {
         Config::default()
     };
 
-    let mut controller: Box<dyn BrightnessController + Send> = if args.dry_run {
-         info!("Using Dummy Controller (Dry Run)");
-         Box::new(DummyController::new())
-    } else {
-        // High Refresh Rate Optimization:
-        // Always prefer BacklightController (sysfs) if available because it is:
-        // 1. Silent (No OSD overlay spam)
-        // 2. Fast (Direct file write vs DBus RTT)
-        // 3. Essential for 60Hz/120Hz updates
-        
-        let mut tried_backlight = false;
-        let mut best_controller: Option<Box<dyn BrightnessController + Send>> = None;
-
-        if config.brightness.method == "auto" || config.brightness.method == "backlight" {
-             if let Ok(c) = BacklightController::auto() {
-                 info!("✅ Using Backlight Controller (sysfs) - Optimized for High Refresh Rate");
-                 best_controller = Some(Box::new(c));
-                 tried_backlight = true;
-             }
-        }
+    // All timing (guard, circadian context, auditor, main loop) reads this clock
+    let clock = system_clock();
 
-        if best_controller.is_none() {
-            let is_kde = std::env::var("KDE_FULL_SESSION").map(|v| v == "true").unwrap_or(false) 
-                         || std::env::var("DESKTOP_SESSION").map(|v| v.contains("plasma")).unwrap_or(false);
-                         
-            if is_kde {
-                info!("Detected KDE Plasma Session.");
-                if !tried_backlight {
-                    // Try backlight again if we skipped it due to config but now are falling back? 
-                    // No, adhere to config. But if config was 'auto' (default), we already tried.
-                }
+    // Every /sys path (backlights, light sensor, batteries) resolves through this
+    let sysfs = config.sysfs_root();
+    if !sysfs.is_system() {
+        info!("Using sysfs tree at {}", sysfs.path().display());
+    }
 
-                if let Ok(c) = core::hardware::KdeBrightnessController::new() {
-                    info!("✅ Using Native KDE Controller (DBus) - Warning: May trigger OSD and latency");
-                    best_controller = Some(Box::new(c));
-                }
-            }
-        }
-        
-        // Final Fallback
-        if best_controller.is_none() {
-            match config.brightness.method.as_str() {
-                "ddcutil" => Box::new(DdcUtilController::new(1)),
-                _ => {
-                    // Try backlight one last time if we haven't
-                    if !tried_backlight {
-                         match BacklightController::auto() {
-                            Ok(c) => Box::new(c),
-                            Err(_) => Box::new(DummyController::new())
-                        }
-                    } else {
-                        Box::new(DummyController::new())
-                    }
-                }
-            }
-        } else {
-            best_controller.unwrap()
+    let discovered: Vec<(String, BoxedController)> = if args.dry_run {
+         // A fake tree is safe to write to, the real one is not
+         let fake = if sysfs.is_system() { Vec::new() } else { BacklightController::discover(&sysfs) };
+         if fake.is_empty() {
+             info!("Using Dummy Controller (Dry Run)");
+             vec![("dummy".to_string(), Box::new(DummyController::new()))]
+         } else {
+             info!("Dry run against {} fake backlight(s)", fake.len());
+             fake
+         }
+    } else {
+        // Every backlight, DDC/CI monitor and (without writable sysfs) desktop DBus output,
+        // falling back to the gamma LUT when none of them can dim.
+        // sysfs backlights stay preferred for the internal panel: silent (no OSD)
+        // and fast enough for the 125Hz loop.
+        let mut found = DisplayRegistry::discover(&config.brightness, &sysfs);
+        if found.is_empty() {
+            warn!("No controllable display found (see the backend probe above), using Dummy Controller");
+            found.push(("dummy".to_string(), Box::new(DummyController::new())));
         }
+        found
     };
 
-    let state_manager = Arc::new(Mutex::new(StateManager::new()));
-    let (initial_b, stored_wake, stored_trans, stored_flashbang) = {
-        let sm = state_manager.lock().unwrap();
-        let state = sm.load();
-        (state.brightness, state.wake_time, state.transition_duration_ms, state.flashbang_protection)
+    let mut state_manager = StateManager::new();
+    let (initial_b, stored_wake, stored_trans, stored_flashbang, stored_easing, stored_fb_easing) = {
+        let state = state_manager.load();
+        (state.brightness, state.wake_time, state.transition_duration_ms, state.flashbang_protection, state.easing, state.flashbang_easing)
     };
     
     let safe_initial = if initial_b < 5.0 { 15.0 } else { initial_b };
     info!("Initial brightness (Persisted): {:.1}%", safe_initial);
-    if let Err(e) = controller.set_brightness(safe_initial) {
-        error!("Failed to set initial brightness: {}", e);
-    } else {
-        info!("✅ Applied initial brightness: {:.1}%", safe_initial);
+
+    let safety_events = SafetyEventLog::default();
+    let mut displays = DisplayRegistry::new();
+    for (id, backend) in discovered {
+        if let Some(display) = managed_display(id, backend, &config, &clock, &safety_events, safe_initial) {
+            displays.add(display);
+        }
+    }
+    for (id, e) in displays.set_all(safe_initial) {
+        error!("Failed to set initial brightness on '{}': {}", id, e);
     }
+    info!("✅ Applied initial brightness: {:.1}% to {} display(s)", safe_initial, displays.len());
+
+    // Colour temperature follows the sun through its own guard
+    let color_backend = if args.dry_run || config.color.method == "off" {
+        None
+    } else {
+        ManagedColorTemperature::discover(&config.color.method, displays.uses_gamma())
+    };
+    let color = color_backend.map(|c| {
+        info!("🌡️ Colour temperature via {}", c.name());
+        ManagedColorTemperature::new(c, clock.clone())
+    });
 
 
 
-    let mut guard = EpilepsyGuard::new(safe_initial);
+    let mut guard = EpilepsyGuard::with_clock(safe_initial, clock.clone());
+    guard.set_space(config.brightness.space);
     guard.set_transition_duration(stored_trans);
-    let guard = Arc::new(Mutex::new(guard));
-    
-    let flashbang_enabled = Arc::new(Mutex::new(stored_flashbang));
-    let fb_enabled_ref = flashbang_enabled.clone();
-    let mut context = core::context::ContextManager::new(&config.location, &config.general.wake_time);
+    guard.set_easing(stored_easing.unwrap_or(config.epilepsy_protection.easing));
+    guard.set_fast_easing(stored_fb_easing.unwrap_or(config.epilepsy_protection.flashbang_easing));
+    guard.set_mode_caps(config.epilepsy_protection.safe_mode_brightness, config.epilepsy_protection.sleep_mode_brightness);
+    // Validated in Config::load_from_file
+    guard.set_mode(config.general.mode.parse().unwrap_or_default());
+
+    // Emergency hotkey (validated in Config::load_from_file)
+    let (hotkey_tx, hotkey_rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+    match config.epilepsy_protection.emergency_hotkey.parse::<core::hotkey::Hotkey>() {
+        Ok(hotkey) => { tokio::spawn(crate::hotkey::listen(hotkey, hotkey_tx)); }
+        Err(e) => warn!("Emergency hotkey disabled: {}", e),
+    }
+
+    let mut context = core::context::ContextManager::with_clock(&config.location, &config.general.wake_time, clock.clone());
     
 
     
+    context.set_kelvin_range(config.color.day_kelvin, config.color.night_kelvin);
+    let b = &config.brightness;
+    context.set_ambient_response(LuxResponse::new(b.lux_curve.clone(), b.min_brightness, b.max_brightness, b.lux_hysteresis), b.ambient_weight);
     if let Some((h, m)) = stored_wake {
         info!("Restoring persisted wake time: {:02}:{:02}", h, m);
         context.set_wake_time(h, m);
     }
     
-    let context = Arc::new(Mutex::new(context));
+    // The 125Hz loop owns the guard, the displays and the schedule; the other
+    // tasks reach them through its shared handles
+    let mut main_loop = MainLoop::new(config.clone(), sysfs.clone(), guard, context, state_manager, clock.clone());
+    *main_loop.flashbang_enabled.lock().unwrap() = stored_flashbang;
+    main_loop.safety_events = safety_events;
+    main_loop.displays = displays;
+    main_loop.color = color;
+    *main_loop.held_displays.lock().unwrap() = main_loop.displays.ids();
+    *main_loop.backend_status.lock().unwrap() = main_loop.displays.backend_status();
     
     // We removed ML entirely from usage, but kept the struct to avoid errors
     // let mut predictor = crate::ml::Predictor::new();
@@ -174,9 +239,24 @@ async fn main() -> Result<()> {
  
 
 
-    let last_heartbeat = Arc::new(Mutex::new(Instant::now()));
-    let weather_modifier = Arc::new(Mutex::new(1.0));
-    let weather_mod_ref = weather_modifier.clone();
+    // Keyboard backlight; a dry run may only write to a fake tree
+    main_loop.keyboard = if !config.keyboard.enabled || (args.dry_run && sysfs.is_system()) {
+        None
+    } else {
+        LedController::discover(&sysfs).map(|led| {
+            info!("⌨️ Keyboard backlight '{}' ({} levels)", led.name(), led.levels());
+            ManagedKeyboard::new(led, config.keyboard.curve.clone(), clock.clone())
+        })
+    };
+
+    // Docking, monitor plugs and GPU switches re-probe the displays
+    let (hotplug_tx, hotplug_rx) = tokio::sync::mpsc::unbounded_channel::<HotplugScan>();
+    if !args.dry_run {
+        tokio::spawn(crate::hotplug::watch(config.brightness.clone(), sysfs.clone(), main_loop.held_displays.clone(), hotplug_tx));
+    }
+
+    let last_heartbeat = Arc::new(Mutex::new(clock.now()));
+    let weather_mod_ref = main_loop.weather_modifier.clone();
     
     tokio::spawn(async move {
         loop {
@@ -205,22 +285,55 @@ async fn main() -> Result<()> {
         }
     });
 
+    // Ambient light: sensor reads block (sysfs, DBus), so they get their own thread
+    let ambient_sensor = if args.dry_run || config.ambient.method == "off" {
+        None
+    } else {
+        AmbientLightSource::discover(&config.ambient.method, &sysfs)
+    };
+    if let Some(sensor) = ambient_sensor {
+        let mut ambient = AmbientLightSource::new(sensor, Duration::from_secs_f64(config.ambient.smoothing_secs), clock.clone());
+        info!("💡 Ambient light via {}", ambient.name());
+        let lux_writer = main_loop.ambient_lux.clone();
+        std::thread::spawn(move || {
+            let mut failing = false;
+            loop {
+                match ambient.poll() {
+                    Ok(lux) => {
+                        failing = false;
+                        *lux_writer.lock().unwrap() = lux;
+                    }
+                    Err(e) => {
+                        if !failing {
+                            warn!("Ambient light sensor read failed: {}", e);
+                        }
+                        // Stale light is worse than the circadian fallback
+                        failing = true;
+                        *lux_writer.lock().unwrap() = None;
+                    }
+                }
+                std::thread::sleep(AMBIENT_POLL_INTERVAL);
+            }
+        });
+    }
+
     // ---------------------------------------------------------
     // ASYNC CONTENT ANALYSIS TASK
     // ---------------------------------------------------------
     // Decouple blocking spectacle calls from the main loop to allow 120Hz smooth transitions.
-    let luma_shared = Arc::new(Mutex::new(None::<f64>));
-    let luma_writer = luma_shared.clone();
-    
-    // We only need one analyzer instance
+    let luma_writer = main_loop.luma.clone();
+    let flash_alert_writer = main_loop.flash_alert.clone();
+    let visual_field = visual_field_px(config.epilepsy_protection.viewing_distance_cm, config.epilepsy_protection.screen_dpi);
+
+    // Flashbang luma: one spectacle capture a second is enough
     tokio::spawn(async move {
         // Delay start slightly to let daemon settle
         tokio::time::sleep(Duration::from_secs(2)).await;
         
         let mut content_analyzer = crate::content::ContentAnalyzer::new();
         loop {
-            if let Some(val) = content_analyzer.get_screen_brightness() {
-                *luma_writer.lock().unwrap() = Some(val);
+            if let Some(frame) = content_analyzer.analyze_screen() {
+                *luma_writer.lock().unwrap() = Some(frame.flashbang_intensity());
             }
             // 100ms interval for content checks is sufficient (10fps for content changes)
             // The main loop will interpolate smoothly at 120Hz.
@@ -228,159 +341,87 @@ async fn main() -> Result<()> {
         }
     });
 
-    let mut content_multiplier = 1.0;
-    
-    // ---------------------------------------------------------
-    // HIGH FREQUENCY MAIN LOOP (125Hz / 8ms)
-    // ---------------------------------------------------------
-    info!("🚀 Starting High-Frequency Loop (8ms / 125Hz) for smooth transitions");
-    
-    let mut tick_count: u64 = 0;
-    let mut interval = tokio::time::interval(Duration::from_millis(8)); 
-
-    loop {
-        tokio::select! {
-            _ = interval.tick() => {
-                 tick_count += 1;
-                 
-                 // 1. Check for new content analysis result (Non-blocking)
-                 let current_luma = { *luma_shared.lock().unwrap() };
-                 let is_fb_enabled = { *fb_enabled_ref.lock().unwrap() }; // Use cloning ref
-                 
-                 // User request: "kısmadı oysa %10'a falan çekmeli"
-                 if let Some(luma) = current_luma {
-                     if is_fb_enabled {
-                         // Aggressive curve: Start dimming at 0.5 (was 0.7)
-                         if luma > 0.5 {
-                             // excess goes from 0.0 to 1.0 (at luma 1.0, excess = 0.5 / 0.5 = 1.0)
-                             let excess = (luma - 0.5) / 0.5;
-                             
-                             // Target multiplier: 
-                             // At luma 1.0 -> excess 1.0 -> target_mult = 1.0 - (1.0 * 0.95) = 0.05 (5% brightness)
-                             let target_mult = 1.0 - (excess * 0.95); 
-                             
-                             if target_mult < content_multiplier {
-                                 // Fast drop (Flashbang protection needs to be instant)
-                                 // Now: Instant application to minimize eye pain
-                                 content_multiplier = target_mult;
-                             } else {
-                                 // Recovery: Let EpilepsyGuard handle smoothing (configurable duration)
-                                 // Prevent oscillation: Do not recover beyond the current target_mult!
-                                 // If target_mult is 0.05 (white screen), we stay at 0.05.
-                                 // 125Hz adjustment: 0.2 per tick at 10Hz was 2.0/sec.
-                                 // At 125Hz, we want similar or faster instant recovery for calculation.
-                                 // 0.02 * 125 = 2.5/sec. Let's use 0.05 to be sure.
-                                 content_multiplier = (content_multiplier + 0.05).min(target_mult);
-                             }
-                         } else {
-                             // Normal content, recover
-                             content_multiplier = (content_multiplier + 0.05).min(1.0);
-                         }
-                     } else {
-                         // Flashbang protection disabled by user
-                         content_multiplier = 1.0;
-                     }
-                 } else {
-                      // No luma data yet
-                 }
-                 
-                 // 2. Main Autopilot Logic
-                 // Was: tick_count % 10 (Every 1s at 10Hz)
-                 // Now: tick_count % 125 (Every 1s at 125Hz)
-                 if tick_count % 125 == 0 {
-                    let mut g = guard.lock().unwrap();
-                    if !g.is_locked && g.mode == core::epilepsy::SafetyMode::Automatic {
-                         if !g.is_in_grace_period(Duration::from_secs(1800)) {
-                             let now = chrono::Utc::now();
-                             
-
-                             
-                             // B. Calculate Brightness Target
-                             let ctx = context.lock().unwrap();
-                             let mut target = ctx.get_circadian_target(now);
-                             
-                             let w_factor = { *weather_modifier.lock().unwrap() };
-                             if w_factor < 0.99 { target *= w_factor; }
-                             if content_multiplier < 0.99 { target *= content_multiplier; }
-                             if is_on_battery() { target *= 0.8; }
-                             
-                             // C. Smart Transition Logic (Epilepsy Friendly)
-                             let diff = (g.current_brightness - target).abs();
-                             let is_dimming_for_safety = target < (g.current_brightness - 1.0) && content_multiplier < 0.99;
-                             
-                             // Rule 1: Safety First. If we need to dim due to Flashbang, do it NOW and FAST.
-                             if is_dimming_for_safety {
-                                 // Use instant transition (200ms) for flashbangs
-                                 g.force_instant_transition(target);
-                             } 
-                             // Rule 2: Circadian Stability. Only change if significant drift or long time.
-                             // Don't change every 2-3 mins for 1% diff.
-                             else if diff > 5.0 {
-                                 // Significant change (e.g. sunset started), apply.
-                                 g.request_transition(target);
-                             }
-                             else if diff > 1.0 && tick_count % 75000 == 0 {
-                                 // Very slow drift check (Every 10 mins = 75000 ticks at 125Hz)
-                                 // Allow small adjustments only rarely.
-                                 g.request_transition(target);
-                             }
-                             
-                             // D. Logging (Every 5s = 625 ticks)
-                             if tick_count % 625 == 0 {
-                                 // let kelvin = ctx.get_kelvin_target(now);
-                                 // Log detailed stats only if verbose or changes happening
-                                 // info!("🔍 STATS | Target: {:.1}% | K: {} | W:x{:.2} | FB:x{:.2}", target, kelvin, w_factor, content_multiplier);
-                             }
-                         }
+    // Flash detection (WCAG 2.3.1) needs many frames a second, from a fast capture
+    match crate::capture::fast_source() {
+        Some(source) => {
+            let mut sampler = FlashSampler::new(source, visual_field, clock.clone());
+            info!("Flash detection sampling via {} every {}ms", sampler.source_name(), FLASH_SAMPLE_INTERVAL.as_millis());
+            std::thread::spawn(move || {
+                let mut failing = false;
+                let mut slow = false;
+                loop {
+                    let started = Instant::now();
+                    match sampler.sample() {
+                        Ok(violation) => {
+                            failing = false;
+                            if let Some(violation) = violation {
+                                *flash_alert_writer.lock().unwrap() = Some(violation);
+                            }
+                        }
+                        Err(e) => {
+                            if !failing {
+                                warn!("Screen capture for flash detection failed: {:#}", e);
+                            }
+                            failing = true;
+                        }
                     }
-                 }
-
-                 // 3. Hardware Tick (Smooth Transitions)
-                 {
-                    let mut g = guard.lock().unwrap();
-                    if let Some(new_val) = g.tick_transition() {
-                          if let Err(e) = controller.set_brightness(new_val) {
-                              error!("HW Error: {}", e);
-                          } else {
-                              // Persist every 5 seconds during transition (625 ticks at 125Hz)
-                              if tick_count % 625 == 0 {
-                                  let ctx = context.lock().unwrap();
-                                  let wt = ctx.get_wake_time();
-                                  drop(ctx);
-                                  let td = g.transition_duration_ms;
-                                  let fb = *fb_enabled_ref.lock().unwrap();
-                                  state_manager.lock().unwrap().save(new_val, Some(wt), td, fb);
-                              }
-                          }
+                    let took = started.elapsed();
+                    if took > MAX_FLASH_SAMPLE_INTERVAL && !slow {
+                        warn!("Screen capture took {}ms, too slow to catch every hazardous flash", took.as_millis());
+                        slow = true;
                     }
-                 }
-            }
-
-
+                    std::thread::sleep(FLASH_SAMPLE_INTERVAL.saturating_sub(took));
+                }
+            });
+        }
+        None => warn!("No fast screen capture in this session, flash hazard detection is off"),
+    }
 
-            result = listener.accept() => {
-                match result {
-                    Ok((stream, _addr)) => {
-                        let guard_ref = guard.clone();
-                        let state_ref = state_manager.clone();
-                        let hb_ref = last_heartbeat.clone();
-                        let ctx_ref = context.clone();
-                        let weather_ref = weather_modifier.clone();
-                        let fb_ref = flashbang_enabled.clone();
-                        
-                        *hb_ref.lock().unwrap() = Instant::now();
-                        
-                        tokio::spawn(async move {
-                            handle_connection(stream, guard_ref, state_ref, hb_ref, ctx_ref, weather_ref, fb_ref).await;
-                        });
-                    }
-                    Err(e) => error!("IPC Accept Error: {}", e),
+    // IPC: every connection gets its own task with handles into the loop's state
+    let (guard, state_manager, context) = (main_loop.guard.clone(), main_loop.state_manager.clone(), main_loop.context.clone());
+    let (weather_modifier, ambient_lux, flashbang_enabled) = (main_loop.weather_modifier.clone(), main_loop.ambient_lux.clone(), main_loop.flashbang_enabled.clone());
+    let (safety_events, backend_status) = (main_loop.safety_events.clone(), main_loop.backend_status.clone());
+    let ipc_clock = clock.clone();
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _addr)) => {
+                    let guard_ref = guard.clone();
+                    let state_ref = state_manager.clone();
+                    let hb_ref = last_heartbeat.clone();
+                    let ctx_ref = context.clone();
+                    let weather_ref = weather_modifier.clone();
+                    let ambient_ref = ambient_lux.clone();
+                    let fb_ref = flashbang_enabled.clone();
+                    let events_ref = safety_events.clone();
+                    let backends_ref = backend_status.clone();
+
+                    *hb_ref.lock().unwrap() = ipc_clock.now();
+
+                    tokio::spawn(async move {
+                        handle_connection(stream, guard_ref, state_ref, hb_ref, ctx_ref, weather_ref, fb_ref, events_ref, backends_ref, ambient_ref).await;
+                    });
                 }
+                Err(e) => error!("IPC Accept Error: {}", e),
             }
         }
-    }
+    });
+
+    // ---------------------------------------------------------
+    // HIGH FREQUENCY MAIN LOOP (125Hz / 8ms)
+    // ---------------------------------------------------------
+    info!("🚀 Starting High-Frequency Loop (8ms / 125Hz) for smooth transitions");
+
+    let interval = tokio::time::interval(Duration::from_millis(8));
+    let ticks = futures_util::stream::unfold(interval, |mut interval| async move {
+        interval.tick().await;
+        Some(((), interval))
+    });
+    main_loop.run(ticks, hotkey_rx, hotplug_rx).await;
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_connection(
     mut stream: tokio::net::UnixStream, 
     guard: Arc<Mutex<EpilepsyGuard>>, 
@@ -389,6 +430,9 @@ async fn handle_connection(
     context: Arc<Mutex<core::context::ContextManager>>,
     weather_modifier: Arc<Mutex<f64>>,
     flashbang_enabled: Arc<Mutex<bool>>,
+    safety_events: SafetyEventLog,
+    backend_status: Arc<Mutex<Vec<BackendStatus>>>,
+    ambient_lux: Arc<Mutex<Option<f64>>>,
 ) {
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use core::ipc::{IpcCommand, IpcResponse};
@@ -399,7 +443,7 @@ async fn handle_connection(
     
     match stream.read(&mut buf).await {
         Ok(n) if n > 0 => {
-             *heartbeat.lock().unwrap() = Instant::now();
+             *heartbeat.lock().unwrap() = guard.lock().unwrap().clock().now();
              
              if let Ok(cmd) = serde_json::from_slice::<IpcCommand>(&buf[..n]) {
                  if !matches!(cmd, IpcCommand::GetInfo | IpcCommand::Heartbeat) {
@@ -463,23 +507,83 @@ async fn handle_connection(
                              }
                              IpcResponse::Ok
                          },
-                         IpcCommand::Freeze(_) => {
-                               g.mode = core::epilepsy::SafetyMode::EmergencyStop;
+                         IpcCommand::SetEasing(easing) => {
+                             g.set_easing(easing);
+                             state_manager.lock().unwrap().set_easing(easing);
+                             // Persist
+                             {
+                                  let ctx = context.lock().unwrap();
+                                  let wt = ctx.get_wake_time();
+                                  let b = g.current_brightness;
+                                  let td = g.transition_duration_ms;
+                                  let fb = *flashbang_enabled.lock().unwrap();
+                                  state_manager.lock().unwrap().save(b, Some(wt), td, fb);
+                             }
+                             IpcResponse::Ok
+                         },
+                         IpcCommand::SetFlashbangEasing(easing) => {
+                             g.set_fast_easing(easing);
+                             state_manager.lock().unwrap().set_flashbang_easing(easing);
+                             // Persist
+                             {
+                                  let ctx = context.lock().unwrap();
+                                  let wt = ctx.get_wake_time();
+                                  let b = g.current_brightness;
+                                  let td = g.transition_duration_ms;
+                                  let fb = *flashbang_enabled.lock().unwrap();
+                                  state_manager.lock().unwrap().save(b, Some(wt), td, fb);
+                             }
+                             IpcResponse::Ok
+                         },
+                         IpcCommand::Freeze(secs) => {
+                               let duration = if secs == 0 { None } else { Some(Duration::from_secs(secs)) };
+                               g.emergency_stop(duration);
                                warn!("EMERGENCY STOP ACTIVATED");
                                logger.log("freeze", g.current_brightness, "EMERGENCY_STOP").ok();
                                IpcResponse::Ok
                          },
+                         IpcCommand::Resume if g.mode != SafetyMode::EmergencyStop => {
+                               IpcResponse::Error("Not stopped".to_string())
+                         },
+                         IpcCommand::Resume => {
+                               g.resume();
+                               logger.log("resume", g.current_brightness, "Automatic").ok();
+
+                               // Start the slow ramp back to the automatic target right away,
+                               // unless the user is holding a manual value
+                               if !g.is_in_grace_period(Duration::from_secs(1800)) {
+                                   let now = g.clock().utc_now();
+                                   let mut ctx = context.lock().unwrap();
+                                   let w_factor = { *weather_modifier.lock().unwrap() };
+                                   let target = ctx.get_automatic_target(now, w_factor, *ambient_lux.lock().unwrap());
+                                   g.request_transition(target);
+                               }
+                               IpcResponse::Ok
+                         },
+                         IpcCommand::SetMode(mode) => {
+                               g.set_mode(mode);
+                               logger.log("mode", g.current_brightness, &format!("{:?}", g.mode)).ok();
+
+                               // Head for the new mode's target unless the user is holding a manual value
+                               if g.mode != SafetyMode::EmergencyStop && !g.is_in_grace_period(Duration::from_secs(1800)) {
+                                   let now = g.clock().utc_now();
+                                   let mut ctx = context.lock().unwrap();
+                                   let w_factor = { *weather_modifier.lock().unwrap() };
+                                   let target = ctx.get_automatic_target(now, w_factor, *ambient_lux.lock().unwrap());
+                                   g.request_transition(target);
+                               }
+                               IpcResponse::Ok
+                         },
                          IpcCommand::ResetAuto => {
                                info!("User requested Auto-Reset (Kontrol Et)");
                                g.last_user_override = None;
                                
-                               let now = chrono::Utc::now();
-                               let ctx = context.lock().unwrap();
+                               let now = g.clock().utc_now();
+                               let mut ctx = context.lock().unwrap();
                                
 
-                               let mut target = ctx.get_circadian_target(now);
                                let w_factor = { *weather_modifier.lock().unwrap() };
-                               if w_factor < 0.99 { target *= w_factor; }
+                               let target = ctx.get_automatic_target(now, w_factor, *ambient_lux.lock().unwrap());
                                
                                g.force_instant_transition(target);
                                IpcResponse::Ok
@@ -494,8 +598,19 @@ async fn handle_connection(
                                    wake_time: format!("{:02}:{:02}", h, m),
                                    transition_duration_ms: g.transition_duration_ms,
                                    flashbang_protection: fb,
+                                   safety_events: safety_events.lock().unwrap().len(),
+                                   easing: g.easing,
+                                   flashbang_easing: g.fast_easing,
+                                   frozen: g.mode == SafetyMode::EmergencyStop,
+                                   freeze_remaining_secs: g.freeze_remaining().map(|d| d.as_secs()),
+                                   mode: g.mode,
+                                   backends: backend_status.lock().unwrap().clone(),
+                                   ambient_lux: *ambient_lux.lock().unwrap(),
                                }
                            }
+                          IpcCommand::GetSafetyEvents => {
+                               IpcResponse::SafetyEvents(safety_events.lock().unwrap().iter().cloned().collect())
+                          }
                       }
                   };
                   
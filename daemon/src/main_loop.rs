@@ -0,0 +1,262 @@
+use core::clock::SharedClock;
+use core::config::Config;
+use core::context::ContextManager;
+use core::epilepsy::{EpilepsyGuard, FlashViolation, SafetyMode};
+use core::failover::BackendStatus;
+use core::hardware::{BrightnessController, DisplayRegistry, ManagedColorTemperature, SafetyEventLog};
+use core::keyboard::ManagedKeyboard;
+use core::sysfs::{is_on_battery, SysfsRoot};
+use futures_util::{Stream, StreamExt};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::{error, warn};
+
+use crate::content::FlashHazard;
+use crate::hotplug::HotplugScan;
+use crate::logging::DataLogger;
+use crate::state::StateManager;
+
+// Everything the 125Hz loop drives. All timing reads `clock` and the loop only
+// advances on `ticks`, so a ManualClock and a stream of ticks simulate hours
+// of daemon behaviour in milliseconds. The shared values are written by the
+// sensor, capture and IPC tasks.
+pub struct MainLoop {
+    pub clock: SharedClock,
+    pub config: Config,
+    pub sysfs: SysfsRoot,
+    pub guard: Arc<Mutex<EpilepsyGuard>>,
+    pub context: Arc<Mutex<ContextManager>>,
+    pub state_manager: Arc<Mutex<StateManager>>,
+    pub logger: DataLogger,
+    pub displays: DisplayRegistry,
+    pub color: Option<ManagedColorTemperature>,
+    pub keyboard: Option<ManagedKeyboard>,
+    pub safety_events: SafetyEventLog,
+    pub held_displays: Arc<Mutex<Vec<String>>>,       // Read by the hotplug watcher
+    pub backend_status: Arc<Mutex<Vec<BackendStatus>>>, // Refreshed for status requests
+    pub weather_modifier: Arc<Mutex<f64>>,
+    pub ambient_lux: Arc<Mutex<Option<f64>>>,
+    pub luma: Arc<Mutex<Option<f64>>>,
+    pub flashbang_enabled: Arc<Mutex<bool>>,
+    pub flash_alert: Arc<Mutex<Option<FlashViolation>>>,
+    pub hotkey_dim_level: f64,
+    content_multiplier: f64,
+    flash_hazard: FlashHazard,
+    tick_count: u64,
+}
+
+impl MainLoop {
+    pub fn new(config: Config, sysfs: SysfsRoot, guard: EpilepsyGuard, context: ContextManager, state_manager: StateManager, clock: SharedClock) -> Self {
+        let hotkey_dim_level = config.brightness.min_brightness;
+        Self {
+            clock,
+            config,
+            sysfs,
+            guard: Arc::new(Mutex::new(guard)),
+            context: Arc::new(Mutex::new(context)),
+            state_manager: Arc::new(Mutex::new(state_manager)),
+            logger: DataLogger::new(),
+            displays: DisplayRegistry::new(),
+            color: None,
+            keyboard: None,
+            safety_events: SafetyEventLog::default(),
+            held_displays: Arc::new(Mutex::new(Vec::new())),
+            backend_status: Arc::new(Mutex::new(Vec::new())),
+            weather_modifier: Arc::new(Mutex::new(1.0)),
+            ambient_lux: Arc::new(Mutex::new(None)),
+            luma: Arc::new(Mutex::new(None)),
+            flashbang_enabled: Arc::new(Mutex::new(true)),
+            flash_alert: Arc::new(Mutex::new(None)),
+            hotkey_dim_level,
+            content_multiplier: 1.0,
+            flash_hazard: FlashHazard::default(),
+            tick_count: 0,
+        }
+    }
+
+    // Runs until `ticks` ends; hotplug scans and hotkey presses are handled between ticks
+    pub async fn run(mut self, ticks: impl Stream<Item = ()>, mut hotkey_rx: UnboundedReceiver<()>, mut hotplug_rx: UnboundedReceiver<HotplugScan>) -> Self {
+        let mut ticks = std::pin::pin!(ticks);
+        loop {
+            tokio::select! {
+                tick = ticks.next() => match tick {
+                    Some(()) => self.tick(),
+                    None => return self,
+                },
+                Some(scan) = hotplug_rx.recv() => self.hotplug(scan),
+                Some(()) = hotkey_rx.recv() => self.hotkey(),
+            }
+        }
+    }
+
+    pub fn hotplug(&mut self, scan: HotplugScan) {
+        let g = self.guard.lock().unwrap();
+        crate::apply_hotplug(&mut self.displays, scan, &g, &self.config, &self.clock, &self.safety_events);
+        *self.held_displays.lock().unwrap() = self.displays.ids();
+        *self.backend_status.lock().unwrap() = self.displays.backend_status();
+    }
+
+    pub fn hotkey(&mut self) {
+        let mut g = self.guard.lock().unwrap();
+        crate::hotkey::on_press(&mut g, self.hotkey_dim_level);
+        self.logger.log("hotkey", g.current_brightness, "EMERGENCY_STOP").ok();
+    }
+
+    pub fn tick(&mut self) {
+        let clock = self.clock.clone();
+        self.tick_count += 1;
+        let tick_count = self.tick_count;
+
+        // 1. Check for new content analysis result (Non-blocking)
+        let current_luma = { *self.luma.lock().unwrap() };
+        let is_fb_enabled = { *self.flashbang_enabled.lock().unwrap() }; // Use cloning ref
+        let fb_threshold = { self.guard.lock().unwrap().profile().flashbang_threshold };
+
+        // User request: "kısmadı oysa %10'a falan çekmeli"
+        if let Some(luma) = current_luma {
+            if is_fb_enabled {
+                // Aggressive curve: Start dimming at the mode's threshold (0.5 in Automatic, was 0.7)
+                if luma > fb_threshold {
+                    // excess goes from 0.0 to 1.0 (at luma 1.0, excess = 1.0)
+                    let excess = (luma - fb_threshold) / (1.0 - fb_threshold);
+
+                    // Target multiplier:
+                    // At luma 1.0 -> excess 1.0 -> target_mult = 1.0 - (1.0 * 0.95) = 0.05 (5% brightness)
+                    let target_mult = 1.0 - (excess * 0.95);
+
+                    if target_mult < self.content_multiplier {
+                        // Fast drop (Flashbang protection needs to be instant)
+                        // Now: Instant application to minimize eye pain
+                        self.content_multiplier = target_mult;
+                    } else {
+                        // Recovery: Let EpilepsyGuard handle smoothing (configurable duration)
+                        // Prevent oscillation: Do not recover beyond the current target_mult!
+                        // If target_mult is 0.05 (white screen), we stay at 0.05.
+                        // 125Hz adjustment: 0.2 per tick at 10Hz was 2.0/sec.
+                        // At 125Hz, we want similar or faster instant recovery for calculation.
+                        // 0.02 * 125 = 2.5/sec. Let's use 0.05 to be sure.
+                        self.content_multiplier = (self.content_multiplier + 0.05).min(target_mult);
+                    }
+                } else {
+                    // Normal content, recover
+                    self.content_multiplier = (self.content_multiplier + 0.05).min(1.0);
+                }
+            } else {
+                // Flashbang protection disabled by user
+                self.content_multiplier = 1.0;
+            }
+        } else {
+            // No luma data yet
+        }
+
+        // 1b. Flash hazard (WCAG 2.3.1): dim at once, the cap holds it while content is flashing
+        // A repeat while still holding escalates to an emergency stop once dimmed
+        let alert = self.flash_alert.lock().unwrap().take();
+        if let Some(violation) = alert {
+            warn!("⚠️ {:?} flash hazard: {:.1} flashes/s - dimming", violation.kind, violation.flashes);
+            self.logger.log("flash_hazard", violation.flashes, "Automatic").ok();
+            self.flash_hazard.report(&mut self.guard.lock().unwrap());
+        }
+        if let Some(cap) = self.flash_hazard.multiplier_cap(clock.now()) {
+            self.content_multiplier = self.content_multiplier.min(cap);
+        }
+        let content_multiplier = self.content_multiplier;
+
+        // 2. Main Autopilot Logic
+        // Was: tick_count % 10 (Every 1s at 10Hz)
+        // Now: tick_count % 125 (Every 1s at 125Hz)
+        if tick_count % 125 == 0 {
+            let mut g = self.guard.lock().unwrap();
+            if !g.is_locked && g.mode != SafetyMode::EmergencyStop {
+                // Manual brightness overrides leave the colour schedule and the keyboard running
+                if let Some(c) = self.color.as_mut() {
+                    c.request_kelvin(self.context.lock().unwrap().get_kelvin_target(clock.utc_now()));
+                }
+                if let Some(k) = self.keyboard.as_mut() {
+                    let w_factor = { *self.weather_modifier.lock().unwrap() };
+                    k.request_target(self.context.lock().unwrap().get_automatic_target(clock.utc_now(), w_factor, *self.ambient_lux.lock().unwrap()));
+                }
+                if !g.is_in_grace_period(Duration::from_secs(1800)) {
+                    let now = clock.utc_now();
+
+                    // B. Calculate Brightness Target
+                    let mut ctx = self.context.lock().unwrap();
+                    let w_factor = { *self.weather_modifier.lock().unwrap() };
+                    let mut target = ctx.get_automatic_target(now, w_factor, *self.ambient_lux.lock().unwrap());
+
+                    if content_multiplier < 0.99 { target *= content_multiplier; }
+                    if is_on_battery(&self.sysfs) { target *= 0.8; }
+
+                    // C. Smart Transition Logic (Epilepsy Friendly)
+                    let diff = (g.current_brightness - target).abs();
+                    let is_dimming_for_safety = target < (g.current_brightness - 1.0) && content_multiplier < 0.99;
+
+                    // Rule 1: Safety First. If we need to dim due to Flashbang, do it NOW and FAST.
+                    if is_dimming_for_safety {
+                        // Use instant transition (200ms) for flashbangs
+                        g.force_instant_transition(target);
+                    }
+                    // Rule 2: Circadian Stability. Only change if significant drift or long time.
+                    // Don't change every 2-3 mins for 1% diff.
+                    else if diff > 5.0 {
+                        // Significant change (e.g. sunset started), apply.
+                        g.request_transition(target);
+                    }
+                    else if diff > 1.0 && tick_count % 75000 == 0 {
+                        // Very slow drift check (Every 10 mins = 75000 ticks at 125Hz)
+                        // Allow small adjustments only rarely.
+                        g.request_transition(target);
+                    }
+
+                    // D. Logging (Every 5s = 625 ticks)
+                    if tick_count % 625 == 0 {
+                        // let kelvin = ctx.get_kelvin_target(now);
+                        // Log detailed stats only if verbose or changes happening
+                        // info!("🔍 STATS | Target: {:.1}% | K: {} | W:x{:.2} | FB:x{:.2}", target, kelvin, w_factor, content_multiplier);
+                    }
+                }
+            }
+        }
+
+        // 3. Hardware Tick (Smooth Transitions)
+        {
+            let mut g = self.guard.lock().unwrap();
+            if self.flash_hazard.tick(&mut g) {
+                warn!("EMERGENCY STOP ACTIVATED (repeated flash hazard)");
+            }
+            // Each display guard follows the master transition with its own offset/scale
+            self.displays.follow(&g);
+            let master_val = g.tick_transition();
+            for (id, e) in self.displays.tick() {
+                error!("HW Error on '{}': {}", id, e);
+            }
+            if tick_count.is_multiple_of(125) {
+                *self.backend_status.lock().unwrap() = self.displays.backend_status();
+            }
+            if let Some(c) = self.color.as_mut() {
+                c.follow(&g);
+                if let Err(e) = c.tick() {
+                    error!("Colour temperature error via {}: {}", c.controller.name(), e);
+                }
+            }
+            if let Some(k) = self.keyboard.as_mut() {
+                k.follow(&g);
+                if let Err(e) = k.tick() {
+                    error!("Keyboard backlight error via {}: {}", k.controller.name(), e);
+                }
+            }
+            if let Some(new_val) = master_val {
+                // Persist every 5 seconds during transition (625 ticks at 125Hz)
+                if tick_count.is_multiple_of(625) {
+                    let ctx = self.context.lock().unwrap();
+                    let wt = ctx.get_wake_time();
+                    drop(ctx);
+                    let td = g.transition_duration_ms;
+                    let fb = *self.flashbang_enabled.lock().unwrap();
+                    self.state_manager.lock().unwrap().save(new_val, Some(wt), td, fb);
+                }
+            }
+        }
+    }
+}
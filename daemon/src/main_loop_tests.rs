@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use crate::logging::DataLogger;
+    use crate::main_loop::MainLoop;
+    use crate::state::StateManager;
+    use chrono::{TimeZone, Utc};
+    use core::clock::{ManualClock, SharedClock};
+    use core::config::{Config, LocationConfig};
+    use core::context::ContextManager;
+    use core::epilepsy::{EpilepsyGuard, FlashKind, FlashViolation, SafetyMode};
+    use core::hardware::{BrightnessController, HardwareError, ManagedDisplay};
+    use core::sysfs::SysfsRoot;
+    use futures_util::StreamExt;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const TICK: Duration = Duration::from_millis(8);
+
+    struct RecordingPanel {
+        writes: Arc<Mutex<Vec<f64>>>,
+    }
+
+    impl BrightnessController for RecordingPanel {
+        fn get_brightness(&self) -> Result<f64, HardwareError> {
+            Ok(self.writes.lock().unwrap().last().copied().unwrap_or(50.0))
+        }
+
+        fn set_brightness(&mut self, value: f64) -> Result<(), HardwareError> {
+            self.writes.lock().unwrap().push(value);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    #[test]
+    fn test_loop_on_manual_clock() {
+        // Istanbul, early afternoon: the circadian target is well above the start
+        let start = Utc.with_ymd_and_hms(2024, 6, 21, 10, 0, 0).unwrap();
+        let manual = ManualClock::shared(start);
+        let clock: SharedClock = manual.clone();
+        let config = Config {
+            location: LocationConfig {
+                latitude: Some(41.0082),
+                longitude: Some(28.9784),
+                method: "manual".to_string(),
+                timezone: "Europe/Istanbul".to_string(),
+            },
+            ..Config::default()
+        };
+        let dir = std::env::temp_dir().join(format!("epilyzer-loop-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let context = ContextManager::with_clock(&config.location, "07:00", clock.clone());
+        let guard = EpilepsyGuard::with_clock(50.0, clock.clone());
+        let state = StateManager::at(dir.join("state.json"));
+        let mut main_loop = MainLoop::new(config, SysfsRoot::new(dir.join("sys")), guard, context, state, clock.clone());
+        main_loop.logger = DataLogger::at(dir.join("history.csv"));
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let panel = RecordingPanel { writes: writes.clone() };
+        main_loop.displays.add(ManagedDisplay::new("panel".into(), Box::new(panel), EpilepsyGuard::with_clock(50.0, clock.clone())));
+        // Calm content on screen
+        *main_loop.luma.lock().unwrap() = Some(0.3);
+
+        let (hotkey_tx, hotkey_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (_hotplug_tx, hotplug_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (guard, flash_alert) = (main_loop.guard.clone(), main_loop.flash_alert.clone());
+        let seconds = |s: u64| s * 125;
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        let recorder = samples.clone();
+
+        // One minute at 125Hz: a flash hazard at 20s, the hotkey at 50s
+        let ticks = futures_util::stream::iter(0..seconds(60)).map(move |tick| {
+            manual.advance(TICK);
+            if tick == seconds(20) {
+                *flash_alert.lock().unwrap() = Some(FlashViolation { kind: FlashKind::General, timestamp: Duration::ZERO, flashes: 6.0 });
+            }
+            if tick == seconds(50) {
+                hotkey_tx.send(()).unwrap();
+            }
+            if tick % seconds(5) == 0 {
+                let g = guard.lock().unwrap();
+                recorder.lock().unwrap().push((tick / 125, g.current_brightness, g.mode));
+            }
+        });
+        // The crate is named `core`, which #[tokio::test] trips over
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let main_loop = runtime.block_on(main_loop.run(ticks, hotkey_rx, hotplug_rx));
+        std::fs::remove_dir_all(&dir).ok();
+
+        let samples = samples.lock().unwrap();
+        let at = |s: u64| samples.iter().find(|(t, _, _)| *t == s).copied().unwrap();
+        // Brightened to the afternoon target within the first seconds
+        let bright = at(15).1;
+        assert!(bright > 80.0, "{:?}", samples);
+        // Dimmed at once by the hazard and held while it lasts
+        assert!(at(25).1 <= bright * 0.2 + 0.1, "{:?}", samples);
+        // Back up once the hold has expired
+        assert!(at(45).1 > 80.0, "{:?}", samples);
+        // The hotkey stops at the dim level, and the panel was written there
+        assert_eq!(at(55).2, SafetyMode::EmergencyStop);
+        assert_eq!(at(55).1, 15.0);
+        assert_eq!(main_loop.guard.lock().unwrap().current_brightness, 15.0);
+        assert_eq!(writes.lock().unwrap().last().copied(), Some(15.0));
+    }
+}
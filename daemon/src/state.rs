@@ -1,3 +1,4 @@
+use core::epilepsy::Easing;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -12,6 +13,10 @@ pub struct AppState {
     pub transition_duration_ms: u64,
     #[serde(default = "default_flashbang")]
     pub flashbang_protection: bool,
+    #[serde(default)]
+    pub easing: Option<Easing>, // None = use the config value
+    #[serde(default)]
+    pub flashbang_easing: Option<Easing>,
 
     pub last_updated: chrono::DateTime<chrono::Utc>,
 }
@@ -31,6 +36,8 @@ impl Default for AppState {
             wake_time: None,
             transition_duration_ms: 750,
             flashbang_protection: true,
+            easing: None,
+            flashbang_easing: None,
 
             last_updated: chrono::Utc::now(),
         }
@@ -39,6 +46,9 @@ impl Default for AppState {
 
 pub struct StateManager {
     path: PathBuf,
+    // Easings set over IPC; the rest follow the config and are not persisted
+    easing: Option<Easing>,
+    flashbang_easing: Option<Easing>,
 }
 
 impl StateManager {
@@ -46,15 +56,21 @@ impl StateManager {
         let path = dirs::data_dir()
             .unwrap_or(PathBuf::from("/tmp"))
             .join("auto_brightness_state.json");
-        Self { path }
+        Self::at(path)
     }
 
-    pub fn load(&self) -> AppState {
+    pub fn at(path: PathBuf) -> Self {
+        Self { path, easing: None, flashbang_easing: None }
+    }
+
+    pub fn load(&mut self) -> AppState {
         if self.path.exists() {
             match fs::read_to_string(&self.path) {
-                Ok(content) => match serde_json::from_str(&content) {
+                Ok(content) => match serde_json::from_str::<AppState>(&content) {
                     Ok(state) => {
                         info!("State loaded: {:?}", state);
+                        self.easing = state.easing;
+                        self.flashbang_easing = state.flashbang_easing;
                         return state;
                     }
                     Err(e) => error!("Failed to parse state file: {}", e),
@@ -66,12 +82,22 @@ impl StateManager {
         AppState::default()
     }
 
+    pub fn set_easing(&mut self, easing: Easing) {
+        self.easing = Some(easing);
+    }
+
+    pub fn set_flashbang_easing(&mut self, easing: Easing) {
+        self.flashbang_easing = Some(easing);
+    }
+
     pub fn save(&self, brightness: f64, wake_time: Option<(u8, u8)>, transition_duration_ms: u64, flashbang_protection: bool) {
         let state = AppState {
             brightness,
             wake_time,
             transition_duration_ms,
             flashbang_protection,
+            easing: self.easing,
+            flashbang_easing: self.flashbang_easing,
             last_updated: chrono::Utc::now(),
         };
 
@@ -7,6 +7,7 @@ use std::rc::Rc;
 use std::time::Duration;
 use tokio::net::UnixStream;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use core::epilepsy::{Easing, SafetyMode};
 use core::ipc::{IpcCommand, IpcResponse};
 
 const APP_ID: &str = "com.autobrightness.gui";
@@ -19,8 +20,14 @@ struct UiState {
     m_spin: gtk::SpinButton,
     trans_slider: Scale,
     fb_switch: gtk::Switch,
+    easing_dd: gtk::DropDown,
+    fb_easing_dd: gtk::DropDown,
+    mode_dd: gtk::DropDown,
 }
 
+// Modes selectable from the dropdown; EmergencyStop has its own button
+const MODES: [SafetyMode; 3] = [SafetyMode::Automatic, SafetyMode::Safe, SafetyMode::Sleep];
+
 fn main() {
     // Create Tokio Runtime
     let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
@@ -197,6 +204,64 @@ fn build_ui(app: &Application) {
     trans_row.add_suffix(&trans_slider);
     adv_card.add(&trans_row);
 
+    // Safety mode
+    let suppress_mode = Rc::new(std::cell::Cell::new(false));
+    let mode_row = ActionRow::new();
+    mode_row.set_title("Mode");
+    mode_row.set_subtitle("Safe and Sleep cap brightness and slow every change");
+    let mode_dd = gtk::DropDown::from_strings(&["Normal", "Safe", "Sleep"]);
+    mode_dd.set_valign(gtk::Align::Center);
+    let suppress_mode_clone = suppress_mode.clone();
+    mode_dd.connect_selected_notify(move |dd| {
+        if suppress_mode_clone.get() { return; }
+        if let Some(&mode) = MODES.get(dd.selected() as usize) {
+            glib::MainContext::default().spawn_local(async move {
+                send_command(IpcCommand::SetMode(mode)).await.ok();
+            });
+        }
+    });
+    mode_row.add_suffix(&mode_dd);
+    adv_card.add(&mode_row);
+
+    // Easing curves (regular transitions and flashbang dimming)
+    let easing_names: Vec<String> = Easing::ALL.iter().map(|e| format!("{:?}", e)).collect();
+    let easing_labels: Vec<&str> = easing_names.iter().map(|n| n.as_str()).collect();
+    let suppress_easing = Rc::new(std::cell::Cell::new(false));
+
+    let easing_row = ActionRow::new();
+    easing_row.set_title("Transition Curve");
+    easing_row.set_subtitle("How circadian and manual changes accelerate");
+    let easing_dd = gtk::DropDown::from_strings(&easing_labels);
+    easing_dd.set_valign(gtk::Align::Center);
+    let suppress_easing_clone = suppress_easing.clone();
+    easing_dd.connect_selected_notify(move |dd| {
+        if suppress_easing_clone.get() { return; }
+        if let Some(&easing) = Easing::ALL.get(dd.selected() as usize) {
+            glib::MainContext::default().spawn_local(async move {
+                send_command(IpcCommand::SetEasing(easing)).await.ok();
+            });
+        }
+    });
+    easing_row.add_suffix(&easing_dd);
+    adv_card.add(&easing_row);
+
+    let fb_easing_row = ActionRow::new();
+    fb_easing_row.set_title("Flashbang Curve");
+    fb_easing_row.set_subtitle("Curve used when dimming for bright content");
+    let fb_easing_dd = gtk::DropDown::from_strings(&easing_labels);
+    fb_easing_dd.set_valign(gtk::Align::Center);
+    let suppress_easing_clone = suppress_easing.clone();
+    fb_easing_dd.connect_selected_notify(move |dd| {
+        if suppress_easing_clone.get() { return; }
+        if let Some(&easing) = Easing::ALL.get(dd.selected() as usize) {
+            glib::MainContext::default().spawn_local(async move {
+                send_command(IpcCommand::SetFlashbangEasing(easing)).await.ok();
+            });
+        }
+    });
+    fb_easing_row.add_suffix(&fb_easing_dd);
+    adv_card.add(&fb_easing_row);
+
     // Force Sync button (Prominent)
     let check_row = ActionRow::new();
     check_row.set_title("Manual Check");
@@ -212,18 +277,32 @@ fn build_ui(app: &Application) {
     check_row.add_suffix(&check_btn);
     adv_card.add(&check_row);
 
-    // Emergency Stop
+    // Emergency Stop (minutes, 0 = until resumed)
     let freeze_row = ActionRow::new();
     freeze_row.set_title("Emergency Stop");
+    freeze_row.set_subtitle("Freeze for N minutes (0 = until Resume)");
+    let freeze_adj = Adjustment::new(5.0, 0.0, 240.0, 1.0, 5.0, 0.0);
+    let freeze_spin = gtk::SpinButton::new(Some(&freeze_adj), 1.0, 0);
+    freeze_spin.set_valign(gtk::Align::Center);
     let freeze_btn = Button::with_label("STOP");
     freeze_btn.set_valign(gtk::Align::Center);
     freeze_btn.add_css_class("destructive-action");
     freeze_btn.connect_clicked(move |_| {
+         let secs = freeze_adj.value() as u64 * 60;
          glib::MainContext::default().spawn_local(async move {
-            send_command(IpcCommand::Freeze(300)).await.ok();
+            send_command(IpcCommand::Freeze(secs)).await.ok();
         });
     });
+    let resume_btn = Button::with_label("Resume");
+    resume_btn.set_valign(gtk::Align::Center);
+    resume_btn.connect_clicked(move |_| {
+         glib::MainContext::default().spawn_local(async move {
+            send_command(IpcCommand::Resume).await.ok();
+        });
+    });
+    freeze_row.add_suffix(&freeze_spin);
     freeze_row.add_suffix(&freeze_btn);
+    freeze_row.add_suffix(&resume_btn);
     adv_card.add(&freeze_row);
     
     main_box.append(&adv_card);
@@ -272,6 +351,9 @@ fn build_ui(app: &Application) {
         m_spin,
         trans_slider,
         fb_switch: fb_switch.clone(),
+        easing_dd,
+        fb_easing_dd,
+        mode_dd,
     }));
 
     // Setup Window Hide on Close
@@ -310,13 +392,35 @@ fn build_ui(app: &Application) {
     let suppress_events_poll = suppress_events.clone();
     let suppress_wake_poll = suppress_wake.clone();
     let suppress_fb_poll = suppress_fb.clone();
+    let suppress_easing_poll = suppress_easing.clone();
+    let suppress_mode_poll = suppress_mode.clone();
 
     
     glib::MainContext::default().spawn_local(async move {
         loop {
-            if let Ok(IpcResponse::Status { brightness, location: _, wake_time, transition_duration_ms, flashbang_protection }) = get_status().await {
+            if let Ok(IpcResponse::Status { brightness, location: _, wake_time, transition_duration_ms, flashbang_protection, easing, flashbang_easing, frozen, freeze_remaining_secs, mode, backends, .. }) = get_status().await {
                  let s = ui_state_clone.borrow();
-                 s.status_label.set_text("Active"); // Short status
+                 // Short status
+                 match (frozen, freeze_remaining_secs) {
+                     (true, Some(secs)) => s.status_label.set_text(&format!("Stopped ({}:{:02} left)", secs / 60, secs % 60)),
+                     (true, None) => s.status_label.set_text("Stopped"),
+                     _ if mode == SafetyMode::Automatic => s.status_label.set_text("Active"),
+                     _ => s.status_label.set_text(&format!("Active ({:?})", mode)),
+                 }
+                 // Which backend drives each display, and why
+                 let backend_lines: Vec<String> = backends.iter()
+                     .map(|b| format!("{}: {} ({})", b.display, b.backend, b.reason))
+                     .collect();
+                 s.status_label.set_tooltip_text(Some(&backend_lines.join("\n")));
+
+                 // Update Mode Dropdown (left as is while stopped)
+                 if let Some(idx) = MODES.iter().position(|m| *m == mode) {
+                     if s.mode_dd.selected() != idx as u32 {
+                         suppress_mode_poll.set(true);
+                         s.mode_dd.set_selected(idx as u32);
+                         suppress_mode_poll.set(false);
+                     }
+                 }
                  
                  // Update Flashbang Switch
                  if s.fb_switch.state() != flashbang_protection {
@@ -325,6 +429,14 @@ fn build_ui(app: &Application) {
                      suppress_fb_poll.set(false);
                  }
 
+                 // Update Easing Dropdowns
+                 let easing_idx = Easing::ALL.iter().position(|e| *e == easing).unwrap_or(0) as u32;
+                 let fb_easing_idx = Easing::ALL.iter().position(|e| *e == flashbang_easing).unwrap_or(0) as u32;
+                 suppress_easing_poll.set(true);
+                 if s.easing_dd.selected() != easing_idx { s.easing_dd.set_selected(easing_idx); }
+                 if s.fb_easing_dd.selected() != fb_easing_idx { s.fb_easing_dd.set_selected(fb_easing_idx); }
+                 suppress_easing_poll.set(false);
+
                  // Update Brightness Slider
                  if (s.slider.value() - brightness).abs() > 1.0 {
                       suppress_events_poll.set(true);
@@ -444,9 +556,9 @@ async fn get_status() -> anyhow::Result<IpcResponse> {
     let bytes = serde_json::to_vec(&IpcCommand::GetInfo)?;
     stream.write_all(&bytes).await?;
     
-    // Read
-    let mut buf = [0; 1024];
-    let n = stream.read(&mut buf).await?;
-    let resp: IpcResponse = serde_json::from_slice(&buf[..n])?;
+    // Read (the daemon closes the stream after replying)
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await?;
+    let resp: IpcResponse = serde_json::from_slice(&buf)?;
     Ok(resp)
 }
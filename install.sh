@@ -86,6 +86,10 @@ cp gui/auto-brightness.desktop ~/.local/share/applications/
 sed -i "s|Exec=auto-brightness-gui|Exec=$HOME/.local/bin/auto-brightness-gui|g" ~/.local/share/applications/auto-brightness.desktop
 sed -i "s|Icon=display-brightness-symbolic|Icon=$HOME/.local/share/icons/hicolor/scalable/apps/auto-brightness.svg|g" ~/.local/share/applications/auto-brightness.desktop
 
+# KWin only hands screenshots (flash detection on Plasma Wayland) to executables listed here
+cp daemon/auto-brightness-daemon.desktop ~/.local/share/applications/
+sed -i "s|Exec=auto-brightness-daemon|Exec=$HOME/.local/bin/auto-brightness-daemon|g" ~/.local/share/applications/auto-brightness-daemon.desktop
+
 # Update desktop database
 update-desktop-database ~/.local/share/applications/ 2>/dev/null || true
 gtk-update-icon-cache ~/.local/share/icons/hicolor/ 2>/dev/null || true
@@ -105,8 +109,9 @@ if [ ! -f /etc/auto-brightness/config.toml ]; then
     echo '
 [general]
 enabled = true
-mode = "normal"
+mode = "normal" # normal, safe, sleep
 log_level = "info"
+# sysfs_root = "/tmp/fake-sys" # fake device tree for demos (or set EPILYZER_SYSFS_ROOT)
 
 [location]
 method = "auto"
@@ -119,12 +124,42 @@ max_changes_per_second = 3.0
 smooth_steps = 50
 emergency_hotkey = "Ctrl+Alt+B"
 safe_mode_brightness = 40.0
+sleep_mode_brightness = 20.0
 
 [brightness]
-method = "backlight"
+method = "auto" # auto (every display), backlight, ddcutil
 min_brightness = 15.0
 max_brightness = 95.0
 default_brightness = 50.0
+space = "perceptual" # perceptual (transitions move in even steps of perceived lightness, CIE L*), linear (even backlight steps)
+gamma = "fallback" # off, fallback (outputs nothing else can dim), extend (also dim below hardware_floor)
+hardware_floor = 10.0
+ambient_weight = 0.7 # share of the target from the light sensor, the rest is circadian
+lux_hysteresis = 25.0 # percent the room light must change before brightness follows
+lux_curve = [
+    { lux = 0.0, brightness = 10.0 },
+    { lux = 10.0, brightness = 33.0 },
+    { lux = 100.0, brightness = 55.0 },
+    { lux = 1000.0, brightness = 78.0 },
+    { lux = 10000.0, brightness = 100.0 },
+]
+
+[color]
+method = "auto" # auto, kde, gnome, gammastep (or redshift), off
+day_kelvin = 6500
+night_kelvin = 3400
+
+[ambient]
+method = "auto" # auto, sensorproxy (iio-sensor-proxy), iio (sysfs), off
+smoothing_secs = 5.0
+
+[keyboard]
+enabled = true # follows the automatic screen target
+curve = [
+    { screen = 10.0, keyboard = 100.0 },
+    { screen = 35.0, keyboard = 50.0 },
+    { screen = 60.0, keyboard = 0.0 },
+]
 ' | sudo tee /etc/auto-brightness/config.toml > /dev/null
 fi
 